@@ -0,0 +1,164 @@
+use super::*;
+
+/// A Kconfig expression, as used by `if` conditions
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    /// A reference to the value of another symbol
+    Symbol(String),
+    /// A literal value, like `y` or `"foo"`
+    Const(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Ne(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate the expression against the current state of `kconfig`. Symbols are true when
+    /// their effective value is `y`, and unset symbols compare equal to `n`.
+    pub fn eval(&self, kconfig: &KConfig) -> bool {
+        self.eval_at(kconfig, kconfig.vars.len())
+    }
+
+    /// The names of the symbols referred to anywhere in the expression, in the order they
+    /// appear. A symbol referred to more than once is listed each time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Expr;
+    ///
+    /// let e = Expr::And(
+    ///     Box::new(Expr::Symbol("A".to_string())),
+    ///     Box::new(Expr::Not(Box::new(Expr::Symbol("B".to_string())))),
+    /// );
+    /// assert_eq!(e.symbols(), ["A", "B"]);
+    /// ```
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut out = vec![];
+        self.collect_symbols(&mut out);
+        out
+    }
+
+    fn collect_symbols<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Self::Symbol(s) => out.push(s),
+            Self::Const(_) => {}
+            Self::Not(e) => e.collect_symbols(out),
+            Self::And(l, r) | Self::Or(l, r) | Self::Eq(l, r) | Self::Ne(l, r) => {
+                l.collect_symbols(out);
+                r.collect_symbols(out);
+            }
+        }
+    }
+
+    /// Change every reference to the symbol `old` into a reference to `new`
+    pub fn rename_symbol(&mut self, old: &str, new: &str) {
+        match self {
+            Self::Symbol(s) if s == old => *s = new.to_string(),
+            Self::Symbol(_) | Self::Const(_) => {}
+            Self::Not(e) => e.rename_symbol(old, new),
+            Self::And(l, r) | Self::Or(l, r) | Self::Eq(l, r) | Self::Ne(l, r) => {
+                l.rename_symbol(old, new);
+                r.rename_symbol(old, new);
+            }
+        }
+    }
+
+    /// Work out the expression's result without looking at any values, if it is the same
+    /// whatever they are. Symbols which aren't defined in `kconfig` are always `n`.
+    pub(crate) fn constant(&self, kconfig: &KConfig) -> Option<bool> {
+        match self {
+            Self::Not(e) => e.constant(kconfig).map(|b| !b),
+            Self::And(l, r) => match (l.constant(kconfig), r.constant(kconfig)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Self::Or(l, r) => match (l.constant(kconfig), r.constant(kconfig)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Self::Eq(l, r) => Some(l.constant_value(kconfig)? == r.constant_value(kconfig)?),
+            Self::Ne(l, r) => Some(l.constant_value(kconfig)? != r.constant_value(kconfig)?),
+            Self::Symbol(_) | Self::Const(_) => {
+                self.constant_value(kconfig).map(|v| v == Value::Bool(true))
+            }
+        }
+    }
+
+    fn constant_value(&self, kconfig: &KConfig) -> Option<Value> {
+        match self {
+            Self::Symbol(s) if kconfig.vars.contains_key(s) => None,
+            Self::Symbol(_) => Some(Value::Bool(false)),
+            Self::Const(v) => Some(v.clone()),
+            e => e.constant(kconfig).map(Value::Bool),
+        }
+    }
+
+    // `depth` is threaded through to `KConfig::resolve` so that conditions which refer back to
+    // themselves through defaults still terminate
+    pub(crate) fn eval_at(&self, kconfig: &KConfig, depth: usize) -> bool {
+        match self {
+            Self::Not(e) => !e.eval_at(kconfig, depth),
+            Self::And(l, r) => l.eval_at(kconfig, depth) && r.eval_at(kconfig, depth),
+            Self::Or(l, r) => l.eval_at(kconfig, depth) || r.eval_at(kconfig, depth),
+            Self::Eq(l, r) => l.value_at(kconfig, depth) == r.value_at(kconfig, depth),
+            Self::Ne(l, r) => l.value_at(kconfig, depth) != r.value_at(kconfig, depth),
+            Self::Symbol(_) | Self::Const(_) => self.value_at(kconfig, depth) == Value::Bool(true),
+        }
+    }
+
+    fn value_at(&self, kconfig: &KConfig, depth: usize) -> Value {
+        match self {
+            Self::Symbol(s) => kconfig.resolve(s, depth).unwrap_or(Value::Bool(false)),
+            Self::Const(v) => v.clone(),
+            e => Value::Bool(e.eval_at(kconfig, depth)),
+        }
+    }
+
+    /// How tightly the expression binds, used to decide where `Display` needs parentheses
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Or(..) => 0,
+            Self::And(..) => 1,
+            Self::Eq(..) | Self::Ne(..) => 2,
+            Self::Not(_) => 3,
+            Self::Symbol(_) | Self::Const(_) => 4,
+        }
+    }
+
+    /// Write an operand of this expression, in parentheses if it binds more loosely. Operators
+    /// group to the left when parsed, so an operand on the `right` of an operator as tight as it
+    /// needs them too.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, e: &Expr, right: bool) -> fmt::Result {
+        if e.precedence() < self.precedence() || right && e.precedence() == self.precedence() {
+            write!(f, "({e})")
+        } else {
+            write!(f, "{e}")
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (l, op, r) = match self {
+            Self::Symbol(s) => return f.write_str(s),
+            Self::Const(Value::String(s)) => return write!(f, "\"{s}\""),
+            Self::Const(v) => return write!(f, "{v}"),
+            Self::Not(e) => {
+                f.write_str("!")?;
+                return self.fmt_operand(f, e, false);
+            }
+            Self::And(l, r) => (l, "&&", r),
+            Self::Or(l, r) => (l, "||", r),
+            Self::Eq(l, r) => (l, "=", r),
+            Self::Ne(l, r) => (l, "!=", r),
+        };
+        self.fmt_operand(f, l, false)?;
+        write!(f, " {op} ")?;
+        self.fmt_operand(f, r, true)
+    }
+}
@@ -1,36 +1,115 @@
+mod expr;
 pub mod parser;
+#[cfg(feature = "serde")]
+mod serialize;
 
-pub struct Error;
-pub type Result<T> = std::result::Result<T, Error>;
 use indexmap::IndexMap;
 use regex::Regex;
+use std::collections::HashSet;
 use std::fmt;
 use std::io::{self, prelude::*, BufReader};
+use std::ops::Range;
+
+pub use expr::Expr;
+
+/// What went wrong, for an [`Error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// There is no symbol with the name given
+    UnknownSymbol,
+    /// A value is of the wrong type for its symbol
+    TypeMismatch,
+    /// A value is outside its symbol's `range`, or doesn't fit in 64 bits
+    OutOfRange,
+    /// Text couldn't be parsed
+    Parse,
+    /// A file couldn't be read
+    Io,
+    /// Anything else, such as a dependency cycle
+    Other,
+}
+
+/// An error from parsing or working with a KConfig
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, ErrorKind, Value};
+///
+/// let mut kconfig = parser::parse_str("config JOBS\n int\n range 1 16\n").unwrap();
+/// let kind = |res: konf::Result<()>| res.unwrap_err().kind();
+///
+/// assert_eq!(kind(kconfig.set("MISSING", Value::Int(4))), ErrorKind::UnknownSymbol);
+/// assert_eq!(kind(kconfig.set("JOBS", Value::Bool(true))), ErrorKind::TypeMismatch);
+/// assert_eq!(kind(kconfig.set("JOBS", Value::Int(17))), ErrorKind::OutOfRange);
+/// assert_eq!(kind(kconfig.set_from_input("JOBS", "four")), ErrorKind::Parse);
+/// assert!(kconfig.set("JOBS", Value::Int(4)).is_ok());
+///
+/// let err = parser::parse_file("/no/such/Kconfig").unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::Io);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    kind: ErrorKind,
+    msg: String,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, msg: impl Into<String>) -> Self {
+        Self {
+            kind,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Put `context`, such as where the error happened, in front of the message
+    pub(crate) fn context(self, context: impl fmt::Display) -> Self {
+        Self::new(self.kind, format!("{context}: {}", self.msg))
+    }
+}
+
+/// Errors made from just a message are parse errors
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Self::new(ErrorKind::Parse, msg)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub enum Type {
+    #[default]
     Bool,
     Int,
     Hex,
     String,
 }
 
-impl Default for Type {
-    fn default() -> Self {
-        Self::Bool
-    }
-}
-
 impl Type {
     /// Create a Type from it's string representation
     ///
     /// # Examples
     ///
     /// ```
-    /// assert_eq!(Type::new("bool"), Type::Bool);
-    /// assert_eq!(Type::new("int"), Type::Int);
-    /// assert_eq!(Type::new("hex"), Type::Hex);
-    /// assert_eq!(Type::new("string"), Type::String);
+    /// use konf::Type;
+    /// assert_eq!(Type::new("bool"), Some(Type::Bool));
+    /// assert_eq!(Type::new("int"), Some(Type::Int));
+    /// assert_eq!(Type::new("hex"), Some(Type::Hex));
+    /// assert_eq!(Type::new("string"), Some(Type::String));
     /// ```
     pub fn new(s: &str) -> Option<Self> {
         let t = match s {
@@ -70,28 +149,128 @@ impl Type {
     pub fn is_string(&self) -> bool {
         matches!(self, Self::String)
     }
-}
 
-impl fmt::Display for Type {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match self {
+    /// The keyword for this type, as [`Type::new`] takes it
+    pub fn as_str(&self) -> &'static str {
+        match self {
             Self::Bool => "bool",
             Self::Int => "int",
             Self::Hex => "hex",
             Self::String => "string",
-        };
-        f.write_str(s)
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
+/// The value of a config
+///
+/// With the `serde` feature enabled, values serialize to the matching JSON/YAML primitive. Hex
+/// values are the exception, and are written as `"0x..."` strings so no precision is lost.
+/// Strings which would read back as hex are written as `{"string": "0x..."}` to keep them apart.
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// use konf::{Type, Value};
+///
+/// let values = vec![
+///     Value::Bool(true),
+///     Value::Int(-12),
+///     Value::from(0x10u64),
+///     Value::String("gcc".to_string()),
+/// ];
+/// let json = serde_json::to_string(&values).unwrap();
+/// assert_eq!(json, r#"[true,-12,"0x10","gcc"]"#);
+/// assert_eq!(serde_json::from_str::<Vec<Value>>(&json).unwrap(), values);
+///
+/// let string = Value::String("0x10".to_string());
+/// let json = serde_json::to_string(&string).unwrap();
+/// assert_eq!(json, r#"{"string":"0x10"}"#);
+/// assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), string);
+///
+/// let json = serde_json::to_string(&Type::Hex).unwrap();
+/// assert_eq!(json, r#""hex""#);
+/// assert_eq!(serde_json::from_str::<Type>(&json).unwrap(), Type::Hex);
+/// # }
+/// ```
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
     Bool(bool), // y/n
     Int(i64),
-    Hex(u64),
+    Hex(Hex),
     String(String),
 }
 
+/// A hex value. Values parsed from text remember how they were written, so that `0x00FF` is
+/// displayed as `0x00FF` rather than `0xff`. Hex values are equal when their numbers are,
+/// however they were written.
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, Hex, Value};
+///
+/// let (name, value) = parser::parse_config_line("CONFIG_BASE=0x00ff").unwrap();
+/// assert_eq!(name, "BASE");
+/// assert_eq!(value.to_string(), "0x00ff");
+/// assert_eq!(value, Value::Hex(Hex::new(0xff)));
+///
+/// // values made in code are written in the usual form
+/// assert_eq!(Hex::new(0xff).to_string(), "0xff");
+/// assert_eq!(Hex::parse("0x0000FFFF").unwrap().to_string(), "0x0000FFFF");
+///
+/// // so a `.config` round-trips unchanged
+/// let mut kconfig = parser::parse_str("config BASE\n hex\n").unwrap();
+/// kconfig.load_from("CONFIG_BASE=0x00ff\n".as_bytes()).unwrap();
+/// assert_eq!(kconfig.save_config_string(), "CONFIG_BASE=0x00ff\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Hex {
+    pub value: u64,
+    /// The text the value was parsed from, if any
+    text: Option<String>,
+}
+
+impl Hex {
+    pub fn new(value: u64) -> Self {
+        Self { value, text: None }
+    }
+
+    /// Parse a `0x` prefixed hex number, remembering how it was written
+    pub fn parse(s: &str) -> Option<Self> {
+        let value = u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()?;
+        Some(Self {
+            value,
+            text: Some(s.to_string()),
+        })
+    }
+}
+
+impl From<u64> for Hex {
+    fn from(v: u64) -> Self {
+        Self::new(v)
+    }
+}
+
+impl PartialEq for Hex {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.text {
+            Some(text) => f.write_str(text),
+            None => write!(f, "{:#x}", self.value),
+        }
+    }
+}
+
 impl From<String> for Value {
     fn from(v: String) -> Self {
         Self::String(v)
@@ -100,7 +279,7 @@ impl From<String> for Value {
 
 impl From<u64> for Value {
     fn from(v: u64) -> Self {
-        Self::Hex(v)
+        Self::Hex(v.into())
     }
 }
 
@@ -117,24 +296,167 @@ impl From<bool> for Value {
 }
 
 impl Value {
-    pub fn as_bool(&self) -> Option<&bool> {
+    /// Interpret the text `s` as a value of type `ty`. Bools are `y`/`n` or `1`/`0`, ints are
+    /// decimal (or `0b`/`0o` prefixed), hex values are `0x` prefixed, and strings are taken as
+    /// they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{Type, Value};
+    ///
+    /// assert_eq!(Value::parse_as("y", Type::Bool), Ok(Value::Bool(true)));
+    /// assert_eq!(Value::parse_as("0", Type::Bool), Ok(Value::Bool(false)));
+    /// assert!(Value::parse_as("yes", Type::Bool).is_err());
+    ///
+    /// assert_eq!(Value::parse_as("-12", Type::Int), Ok(Value::Int(-12)));
+    /// assert!(Value::parse_as("0x10", Type::Int).is_err());
+    /// assert!(Value::parse_as("twelve", Type::Int).is_err());
+    ///
+    /// assert_eq!(Value::parse_as("0x10", Type::Hex), Ok(Value::from(0x10u64)));
+    /// assert!(Value::parse_as("10", Type::Hex).is_err());
+    ///
+    /// assert_eq!(Value::parse_as("gcc", Type::String), Ok(Value::String("gcc".to_string())));
+    ///
+    /// let err = Value::parse_as("0xfoo", Type::Hex).unwrap_err();
+    /// assert_eq!(err.to_string(), "`0xfoo` is not a valid hex value");
+    /// ```
+    pub fn parse_as(s: &str, ty: Type) -> Result<Value> {
+        let v = match ty {
+            Type::Bool => match s {
+                "y" | "1" => Some(Value::Bool(true)),
+                "n" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            Type::Int => parser::parse_int(s).map(Value::Int),
+            Type::Hex => Hex::parse(s).map(Value::Hex),
+            Type::String => Some(Value::String(s.to_string())),
+        };
+        v.ok_or_else(|| {
+            let msg = format!("`{s}` is not a valid {ty} value");
+            Error::new(ErrorKind::Parse, msg)
+        })
+    }
+
+    /// Build a value from the name of its type, as given by [`kind_str`](Value::kind_str), and
+    /// its text, read as [`parse_as`](Value::parse_as) does
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// for value in [
+    ///     Value::Bool(true),
+    ///     Value::Int(-12),
+    ///     Value::from(0x10u64),
+    ///     Value::String("gcc".to_string()),
+    /// ] {
+    ///     let back = Value::from_kind_and_text(value.kind_str(), &value.to_string());
+    ///     assert_eq!(back, Ok(value));
+    /// }
+    ///
+    /// let err = Value::from_kind_and_text("tristate", "m").unwrap_err();
+    /// assert_eq!(err.to_string(), "unknown type `tristate`");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `kind` isn't a type, or `text` isn't a value of it.
+    pub fn from_kind_and_text(kind: &str, text: &str) -> Result<Value> {
+        let ty = Type::new(kind)
+            .ok_or_else(|| Error::new(ErrorKind::Parse, format!("unknown type `{kind}`")))?;
+        Value::parse_as(text, ty)
+    }
+
+    /// The name of this value's type: `"bool"`, `"int"`, `"hex"` or `"string"`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::Bool(true).kind_str(), "bool");
+    /// assert_eq!(Value::from(0x10u64).kind_str(), "hex");
+    /// ```
+    pub fn kind_str(&self) -> &'static str {
+        self.type_of().as_str()
+    }
+
+    /// The value of a bool, or `None` for any other type of value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::Bool(true).as_bool(), Some(true));
+    /// assert_eq!(Value::Int(1).as_bool(), None);
+    /// assert_eq!(Value::from(1u64).as_bool(), None);
+    /// assert_eq!(Value::String("y".to_string()).as_bool(), None);
+    /// ```
+    pub fn as_bool(&self) -> Option<bool> {
         if let Self::Bool(v) = self {
-            Some(v)
+            Some(*v)
         } else {
             None
         }
     }
 
-    pub fn as_int(&self) -> Option<&i64> {
+    /// The value of an int, or `None` for any other type of value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::Int(-4).as_int(), Some(-4));
+    /// assert_eq!(Value::Bool(true).as_int(), None);
+    /// assert_eq!(Value::from(4u64).as_int(), None);
+    /// assert_eq!(Value::String("4".to_string()).as_int(), None);
+    /// ```
+    pub fn as_int(&self) -> Option<i64> {
         if let Self::Int(v) = self {
-            Some(v)
+            Some(*v)
         } else {
             None
         }
     }
 
-    pub fn as_hex(&self) -> Option<&u64> {
+    /// The value of a hex value, or `None` for any other type of value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::from(0x10u64).as_hex(), Some(0x10));
+    /// assert_eq!(Value::Bool(true).as_hex(), None);
+    /// assert_eq!(Value::Int(16).as_hex(), None);
+    /// assert_eq!(Value::String("0x10".to_string()).as_hex(), None);
+    /// ```
+    pub fn as_hex(&self) -> Option<u64> {
         if let Self::Hex(v) = self {
+            Some(v.value)
+        } else {
+            None
+        }
+    }
+
+    /// The value of a string, or `None` for any other type of value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::String("gcc".to_string()).as_str(), Some("gcc"));
+    /// assert_eq!(Value::Bool(true).as_str(), None);
+    /// assert_eq!(Value::Int(1).as_str(), None);
+    /// assert_eq!(Value::from(1u64).as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        if let Self::String(v) = self {
             Some(v)
         } else {
             None
@@ -148,6 +470,72 @@ impl Value {
             None
         }
     }
+
+    /// The type of this value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{Type, Value};
+    ///
+    /// assert_eq!(Value::Bool(false).type_of(), Type::Bool);
+    /// assert_eq!(Value::Int(1).type_of(), Type::Int);
+    /// assert_eq!(Value::from(1u64).type_of(), Type::Hex);
+    /// assert_eq!(Value::String(String::new()).type_of(), Type::String);
+    /// ```
+    pub fn type_of(&self) -> Type {
+        match self {
+            Self::Bool(_) => Type::Bool,
+            Self::Int(_) => Type::Int,
+            Self::Hex(_) => Type::Hex,
+            Self::String(_) => Type::String,
+        }
+    }
+
+    /// The value of an int or hex as a number wide enough for either
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::Value;
+    ///
+    /// assert_eq!(Value::Int(-1).numeric(), Some(-1));
+    /// assert_eq!(Value::from(u64::MAX).numeric(), Some(u64::MAX as i128));
+    /// assert_eq!(Value::Bool(true).numeric(), None);
+    /// ```
+    pub fn numeric(&self) -> Option<i128> {
+        match self {
+            Self::Int(i) => Some(*i as i128),
+            Self::Hex(h) => Some(h.value as i128),
+            Self::Bool(_) | Self::String(_) => None,
+        }
+    }
+}
+
+/// Values are only ordered against values of the same type: ints as signed numbers, hex values
+/// as unsigned ones, strings by their text and `n` before `y`
+///
+/// # Examples
+///
+/// ```
+/// use konf::Value;
+///
+/// assert!(Value::Int(-1) < Value::Int(1));
+/// assert!(Value::from(0xffu64) > Value::from(0x10u64));
+/// assert!(Value::from(u64::MAX) > Value::from(0u64));
+/// assert_eq!(Value::Int(1).partial_cmp(&Value::from(1u64)), None);
+/// assert!(!(Value::Int(1) <= Value::String("1".to_string())));
+/// ```
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a.partial_cmp(b),
+            (Self::Int(a), Self::Int(b)) => a.partial_cmp(b),
+            (Self::Hex(a), Self::Hex(b)) => a.value.partial_cmp(&b.value),
+            (Self::String(a), Self::String(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Value {
@@ -156,14 +544,230 @@ impl fmt::Display for Value {
             Self::Bool(true) => write!(f, "y")?,
             Self::Bool(false) => write!(f, "n")?,
             Self::Int(i) => write!(f, "{}", i)?,
-            Self::Hex(i) => write!(f, "{:#x}", i)?,
+            Self::Hex(h) => write!(f, "{}", h)?,
             Self::String(s) => f.write_str(s)?,
         };
         Ok(())
     }
 }
 
-#[derive(Debug)]
+/// The `default` of a config: either a literal value, or the name of another symbol whose value
+/// should be used. Any `if` condition is kept alongside it in [`Variable::defaults`].
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, DefaultKind, Expr, Value};
+///
+/// let kconfig = parser::parse_str("config FOO\n bool\nconfig BAR\n bool\n default y if FOO\n")
+///     .unwrap();
+/// let (default, cond) = &kconfig.vars["BAR"].defaults[0];
+/// assert_eq!(*default, DefaultKind::Literal(Value::Bool(true)));
+/// assert_eq!(*cond, Some(Expr::Symbol("FOO".to_string())));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum DefaultKind {
+    Literal(Value),
+    Symbol(String),
+}
+
+impl From<Value> for DefaultKind {
+    fn from(v: Value) -> Self {
+        Self::Literal(v)
+    }
+}
+
+impl DefaultKind {
+    pub fn as_literal(&self) -> Option<&Value> {
+        if let Self::Literal(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_symbol(&self) -> Option<&str> {
+        if let Self::Symbol(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+}
+
+/// Written as in a Kconfig file, so string values are quoted
+///
+/// # Examples
+///
+/// ```
+/// use konf::{DefaultKind, Value};
+///
+/// assert_eq!(DefaultKind::from(Value::String("gcc".to_string())).to_string(), "\"gcc\"");
+/// assert_eq!(DefaultKind::Symbol("CC".to_string()).to_string(), "CC");
+/// ```
+impl fmt::Display for DefaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Literal(v) => f.write_str(&config_value(v)),
+            Self::Symbol(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A symbol declared by a `config` entry
+///
+/// # Examples
+///
+/// The properties of a `config` can be given in any order:
+///
+/// ```
+/// use konf::{parser, Type, Value};
+///
+/// let kconfig = parser::parse_str("config FOO\n  default y\n  bool\n").unwrap();
+/// assert_eq!(kconfig.vars["FOO"].ty, Some(Type::Bool));
+/// assert_eq!(kconfig.vars["FOO"].defaults, vec![(Value::Bool(true).into(), None)]);
+/// ```
+///
+/// Every type can be followed by the prompt shown for the config, which may be empty:
+///
+/// ```
+/// use konf::{parser, Type, Value};
+///
+/// for ty in ["bool", "int", "hex", "string"] {
+///     let kconfig = parser::parse_str(&format!("config N\n  {ty} \"Number of things\"\n")).unwrap();
+///     assert_eq!(kconfig.vars["N"].ty, Type::new(ty));
+///     assert_eq!(kconfig.vars["N"].desc.as_deref(), Some("Number of things"));
+/// }
+///
+/// let kconfig = parser::parse_str("config CMDLINE\n  string \"\"\n  default \"\"\n").unwrap();
+/// assert_eq!(kconfig.vars["CMDLINE"].desc.as_deref(), Some(""));
+/// assert_eq!(kconfig.vars["CMDLINE"].defaults, vec![(Value::String(String::new()).into(), None)]);
+/// ```
+///
+/// `def_bool` declares a bool and gives its default at once. There are no tristates, so
+/// `def_tristate` is taken the same way:
+///
+/// ```
+/// use konf::{parser, Expr, Type, Value};
+///
+/// let kconfig = parser::parse_str(
+///     "config FOO\n def_bool n\nconfig BAR\n def_bool y if FOO\nconfig BAZ\n def_tristate FOO\n",
+/// )
+/// .unwrap();
+/// assert_eq!(kconfig.vars["FOO"].ty, Some(Type::Bool));
+/// assert_eq!(kconfig.default_value("FOO"), Some(Value::Bool(false)));
+/// let foo = Some(Expr::Symbol("FOO".to_string()));
+/// assert_eq!(kconfig.vars["BAR"].defaults, [(Value::Bool(true).into(), foo)]);
+/// assert_eq!(kconfig.vars["BAZ"].ty, Some(Type::Bool));
+/// ```
+///
+/// A config with more than one `depends on` needs all of them to hold:
+///
+/// ```
+/// use konf::{parser, Value};
+///
+/// let mut kconfig = parser::parse_str(
+///     "config A\n bool\nconfig B\n bool\nconfig C\n bool\n depends on A\n depends on B\n",
+/// )
+/// .unwrap();
+/// assert_eq!(kconfig.vars["C"].depends.as_ref().unwrap().to_string(), "A && B");
+///
+/// kconfig.vars["A"].value = Some(Value::Bool(true));
+/// assert!(!kconfig.visible("C"));
+/// kconfig.vars["B"].value = Some(Value::Bool(true));
+/// assert!(kconfig.visible("C"));
+/// ```
+///
+/// Declaring a config again adds to it, which is how architectures extend a generic symbol:
+///
+/// ```
+/// use konf::{parser, Value, Variable};
+///
+/// let kconfig = parser::parse_str(
+///     r#"
+///     config PCI
+///         bool
+///     config X86
+///         bool
+///     config FOO
+///         bool "Foo"
+///         depends on PCI
+///         select X86
+///     config FOO
+///         depends on X86
+///         default y
+///     "#,
+/// )
+/// .unwrap();
+/// let foo = &kconfig.vars["FOO"];
+/// assert_eq!(foo.depends.as_ref().unwrap().to_string(), "PCI && X86");
+/// assert_eq!(foo.desc.as_deref(), Some("Foo"));
+/// assert_eq!(foo.selects, [("X86".to_string(), None)]);
+/// assert_eq!(foo.defaults, [(Value::Bool(true).into(), None)]);
+///
+/// // it is only listed once, where it was first declared, so it survives being written out
+/// assert_eq!(kconfig.num_vars(), 3);
+/// let again = parser::parse_str(&kconfig.to_string()).unwrap();
+/// let unspanned = |var: &Variable| Variable { span: None, ..var.clone() };
+/// assert_eq!(unspanned(&again.vars["FOO"]), unspanned(foo));
+/// let canonical = kconfig.fmt_canonical();
+/// assert_eq!(parser::parse_str(&canonical).unwrap().fmt_canonical(), canonical);
+/// ```
+///
+/// A config with no properties at all is still declared, with nothing set:
+///
+/// ```
+/// use konf::{parser, Entry, Variable};
+///
+/// let kconfig = parser::parse_str("config FOO\nconfig BAR\n").unwrap();
+/// assert_eq!(kconfig.vars.len(), 2);
+/// assert_eq!(
+///     kconfig.root.entries,
+///     [Entry::Variable("FOO".to_string()), Entry::Variable("BAR".to_string())]
+/// );
+/// for name in ["FOO", "BAR"] {
+///     let var = Variable { span: None, ..kconfig.vars[name].clone() };
+///     assert_eq!(var, Variable::new(name));
+/// }
+/// ```
+///
+/// Parsed configs know where they were declared:
+///
+/// ```
+/// use konf::parser;
+///
+/// let text = "menu \"Build\"\nconfig DEBUG\n    bool\nendmenu\n";
+/// let kconfig = parser::parse_str(text).unwrap();
+/// let span = kconfig.vars["DEBUG"].span.clone().unwrap();
+/// assert_eq!(&text[span], "config DEBUG");
+/// let span = kconfig.find_menu("Build").unwrap().span.clone().unwrap();
+/// assert_eq!(&text[span], "menu \"Build\"");
+/// assert_eq!(konf::Variable::new("DEBUG").span, None);
+/// ```
+///
+/// Help text keeps its paragraphs, and ends at the first line indented less than it:
+///
+/// ```
+/// use konf::parser;
+///
+/// let kconfig = parser::parse_str(
+///     "config SMP\n    bool\n    help\n      Run on more than one CPU.\n\n      Say Y if unsure.\n\nconfig NUMA\n    bool\n",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     kconfig.vars["SMP"].help.as_deref(),
+///     Some("Run on more than one CPU.\n\nSay Y if unsure.")
+/// );
+/// assert!(kconfig.vars.contains_key("NUMA"));
+///
+/// // the old `---help---` spelling is the same as `help`
+/// let old = parser::parse_str(
+///     "config SMP\n    bool\n    ---help---\n      Run on more than one CPU.\n\n      Say Y if unsure.\n\nconfig NUMA\n    bool\n",
+/// )
+/// .unwrap();
+/// assert_eq!(old.vars["SMP"].help, kconfig.vars["SMP"].help);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
 pub struct Variable {
     /// The name of the config
     pub name: String,
@@ -173,8 +777,26 @@ pub struct Variable {
     pub desc: Option<String>,
     /// The current value. Inherits from `default`
     pub value: Option<Value>,
-    /// The default value
-    pub default: Option<Value>,
+    /// The default values, each with an optional `if` condition. The first default whose
+    /// condition holds is used
+    pub defaults: Vec<(DefaultKind, Option<Expr>)>,
+    /// The inclusive `(min, max)` bounds of an int or hex config. Either end may be another
+    /// symbol, whose value is used
+    pub range: Option<(DefaultKind, DefaultKind)>,
+    /// The `depends on` expression which must hold for the config to be visible. Several
+    /// `depends on` lines are joined with `&&`.
+    pub depends: Option<Expr>,
+    /// The symbols this config forces to `y` when it is `y`, each with an optional `if`
+    /// condition
+    pub selects: Vec<(String, Option<Expr>)>,
+    /// The symbols this config defaults to `y` when it is `y`, each with an optional `if`
+    /// condition. Unlike `select`, an implied symbol can still be set to `n`
+    pub implies: Vec<(String, Option<Expr>)>,
+    /// The `help` text, without its indentation. Paragraphs are separated by a blank line.
+    pub help: Option<String>,
+    /// Where the `config NAME` line is, as a byte range in the text of the file it was parsed
+    /// from
+    pub span: Option<Range<usize>>,
 }
 
 impl Variable {
@@ -184,59 +806,339 @@ impl Variable {
             ty: None,
             desc: None,
             value: None,
-            default: None,
+            defaults: vec![],
+            range: None,
+            depends: None,
+            selects: vec![],
+            implies: vec![],
+            help: None,
+            span: None,
+        }
+    }
+
+    /// Change every reference this variable makes to the symbol `old` into one to `new`
+    fn rename_references(&mut self, old: &str, new: &str) {
+        if let Some(e) = &mut self.depends {
+            e.rename_symbol(old, new);
+        }
+        for (default, cond) in &mut self.defaults {
+            if default.as_symbol() == Some(old) {
+                *default = DefaultKind::Symbol(new.to_string());
+            }
+            if let Some(cond) = cond {
+                cond.rename_symbol(old, new);
+            }
+        }
+        for (target, cond) in self.selects.iter_mut().chain(&mut self.implies) {
+            if target == old {
+                *target = new.to_string();
+            }
+            if let Some(cond) = cond {
+                cond.rename_symbol(old, new);
+            }
+        }
+        if let Some((min, max)) = &mut self.range {
+            for end in [min, max] {
+                if end.as_symbol() == Some(old) {
+                    *end = DefaultKind::Symbol(new.to_string());
+                }
+            }
+        }
+    }
+
+    /// Convert `value` to this variable's type where nothing is lost in doing so, as when a
+    /// hex symbol is written as a plain number. Other mismatches are an error.
+    fn coerce(&self, value: Value) -> Result<Value> {
+        match (self.ty, value) {
+            (Some(Type::Hex), Value::Int(i)) if i >= 0 => Ok(Value::from(i as u64)),
+            (Some(Type::Int), Value::Hex(h)) if i64::try_from(h.value).is_ok() => {
+                Ok(Value::Int(h.value as i64))
+            }
+            (_, value) => self.check_type(&value).map(|_| value),
+        }
+    }
+
+    /// Check that `value` can be given to this variable. Untyped variables take any value
+    fn check_type(&self, value: &Value) -> Result<()> {
+        match self.ty {
+            Some(ty) if ty != value.type_of() => {
+                let msg = format!(
+                    "symbol {} expects {ty} but got {}",
+                    self.name,
+                    value.type_of()
+                );
+                Err(Error::new(ErrorKind::TypeMismatch, msg))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The names of the symbols this variable's value or visibility can depend on: those in its
+    /// `depends on` expression, its `default` symbols and their conditions, and its `range`
+    pub(crate) fn references(&self) -> Vec<&str> {
+        let mut out = vec![];
+        if let Some(e) = &self.depends {
+            out.extend(e.symbols());
+        }
+        for (default, cond) in &self.defaults {
+            out.extend(default.as_symbol());
+            if let Some(cond) = cond {
+                out.extend(cond.symbols());
+            }
+        }
+        if let Some((min, max)) = &self.range {
+            out.extend(min.as_symbol());
+            out.extend(max.as_symbol());
+        }
+        out
+    }
+}
+
+/// Format a value as it appears on the right of a `.config` line, where strings are quoted
+fn config_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => quote(s),
+        v => v.to_string(),
+    }
+}
+
+/// Put `s` in double quotes, escaping any `"` or `\` in it with a `\`
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// How [`KConfig::format_with`] lays out Kconfig text
+///
+/// # Examples
+///
+/// ```
+/// use konf::FormatOptions;
+///
+/// assert_eq!(FormatOptions::default().indent, "    ");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// What each level of nesting is indented by. Defaults to four spaces
+    pub indent: String,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: "    ".to_string(),
         }
     }
 }
 
-fn spaces(f: &mut fmt::Formatter, depth: i32) -> fmt::Result {
+fn spaces(f: &mut fmt::Formatter, opts: &FormatOptions, depth: i32) -> fmt::Result {
     for _i in 0..depth {
-        write!(f, "    ")?;
+        f.write_str(&opts.indent)?;
     }
     Ok(())
 }
 
 impl Variable {
-    fn pretty_format(&self, f: &mut fmt::Formatter, depth: i32) -> fmt::Result {
-        spaces(f, depth)?;
+    // Properties are always written in the same order, whatever order they were declared in,
+    // so that formatting a file twice gives the same result. Keep it in line with the `Display`
+    // docs below.
+    fn pretty_format(
+        &self,
+        f: &mut fmt::Formatter,
+        opts: &FormatOptions,
+        depth: i32,
+    ) -> fmt::Result {
+        spaces(f, opts, depth)?;
         writeln!(f, "config {}", self.name)?;
         if let Some(t) = self.ty {
-            spaces(f, depth + 1)?;
+            spaces(f, opts, depth + 1)?;
             write!(f, "{t}")?;
             if let Some(d) = &self.desc {
-                write!(f, " \"{d}\"")?;
+                write!(f, " {}", quote(d))?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(e) = &self.depends {
+            spaces(f, opts, depth + 1)?;
+            writeln!(f, "depends on {e}")?;
+        }
+        for (d, cond) in &self.defaults {
+            spaces(f, opts, depth + 1)?;
+            write!(f, "default {d}")?;
+            if let Some(cond) = cond {
+                write!(f, " if {cond}")?;
             }
             writeln!(f)?;
         }
-        if let Some(d) = &self.default {
-            spaces(f, depth + 1)?;
-            writeln!(f, "default {d}")?;
+        if let Some((min, max)) = &self.range {
+            spaces(f, opts, depth + 1)?;
+            writeln!(f, "range {min} {max}")?;
+        }
+        for (kw, targets) in [("select", &self.selects), ("imply", &self.implies)] {
+            for (target, cond) in targets {
+                spaces(f, opts, depth + 1)?;
+                write!(f, "{kw} {target}")?;
+                if let Some(cond) = cond {
+                    write!(f, " if {cond}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        if let Some(help) = &self.help {
+            spaces(f, opts, depth + 1)?;
+            writeln!(f, "help")?;
+            for line in help.lines() {
+                if !line.is_empty() {
+                    spaces(f, opts, depth + 1)?;
+                    write!(f, "  {line}")?;
+                }
+                writeln!(f)?;
+            }
         }
 
         if let Some(v) = &self.value {
-            spaces(f, depth + 1)?;
+            spaces(f, opts, depth + 1)?;
             writeln!(f, "# current {v}")?;
         }
         Ok(())
     }
 }
 
+/// Format the variable as a Kconfig `config` entry. Its properties come in a fixed order: the
+/// type and prompt, `depends on`, each `default`, `range`, each `select` then each `imply`, the
+/// `help` text, and lastly the current value as a comment.
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, Value};
+///
+/// let mut kconfig = parser::parse_str(
+///     r#"
+///     config JOBS
+///         range 1 MAX_JOBS
+///         imply PARALLEL
+///         default 8 if SMP
+///         select THREADS
+///         depends on BUILD
+///         int "Parallel jobs"
+///         help
+///           How many jobs to run at once.
+///         default 1
+///     "#,
+/// )
+/// .unwrap();
+/// kconfig.vars["JOBS"].value = Some(Value::Int(4));
+/// assert_eq!(
+///     kconfig.vars["JOBS"].to_string(),
+///     concat!(
+///         "    config JOBS\n",
+///         "        int \"Parallel jobs\"\n",
+///         "        depends on BUILD\n",
+///         "        default 8 if SMP\n",
+///         "        default 1\n",
+///         "        range 1 MAX_JOBS\n",
+///         "        select THREADS\n",
+///         "        imply PARALLEL\n",
+///         "        help\n",
+///         "          How many jobs to run at once.\n",
+///         "        # current 4\n",
+///     )
+/// );
+/// ```
 impl fmt::Display for Variable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.pretty_format(f, 1)
+        self.pretty_format(f, &FormatOptions::default(), 1)
     }
 }
 
-#[derive(Debug)]
+/// An entry in a [`Menu`]
+///
+/// # Examples
+///
+/// Comments are only kept when parsing with [`keep_comments`](parser::Options::keep_comments),
+/// and are printed back out above the entry that followed them:
+///
+/// ```
+/// use konf::parser::{self, Options};
+/// use konf::Entry;
+///
+/// let path = std::env::temp_dir().join(format!("konf-entry-comments-{}", std::process::id()));
+/// std::fs::write(&path, "# Build with debug info\nconfig DEBUG\n    bool \"Debug\"\n").unwrap();
+///
+/// let options = Options { keep_comments: true, ..Default::default() };
+/// let kconfig = parser::parse_file_with_options(&path, &options).unwrap();
+/// assert!(matches!(
+///     &kconfig.root.entries[..],
+///     [Entry::SourceComment(c), Entry::Variable(v)] if c == "Build with debug info" && v == "DEBUG"
+/// ));
+///
+/// let text = kconfig.to_string();
+/// assert!(text.contains("# Build with debug info\nconfig DEBUG\n"));
+///
+/// // the output parses back to the same tree
+/// std::fs::write(&path, &text).unwrap();
+/// let again = parser::parse_file_with_options(&path, &options).unwrap();
+/// assert!(matches!(
+///     &again.root.entries[..],
+///     [Entry::SourceComment(c), Entry::Variable(v)] if c == "Build with debug info" && v == "DEBUG"
+/// ));
+///
+/// // by default they are dropped
+/// assert_eq!(parser::parse_file(&path).unwrap().root.entries.len(), 1);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
 pub enum Entry {
     Variable(String),
     Menu(Menu),
+    /// A `#` comment from the Kconfig source, without the `#`
+    SourceComment(String),
+    Choice(Choice),
+}
+
+/// A `choice` block: a group of bool configs of which exactly one should be `y`, or at most one
+/// if the choice is `optional`
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser;
+///
+/// // a config declared before the choice still becomes a member when declared in it
+/// let kconfig = parser::parse_str(
+///     "config GCC\n bool \"GCC\"\nchoice\n prompt \"cc\"\nconfig GCC\n bool\nconfig CLANG\n bool\nendchoice\n",
+/// )
+/// .unwrap();
+/// assert_eq!(kconfig.choices()[0].members, ["GCC", "CLANG"]);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Choice {
+    /// The prompt shown for the choice
+    pub prompt: Option<String>,
+    /// Whether it's fine for no member to be chosen
+    pub optional: bool,
+    /// The member chosen when none has been chosen explicitly
+    pub default: Option<String>,
+    /// The names of the configs to choose between
+    pub members: Vec<String>,
+}
+
+impl Choice {
+    /// A name for the choice to use in messages: its prompt, or failing that its members
+    fn describe(&self) -> String {
+        match &self.prompt {
+            Some(prompt) => format!("choice \"{prompt}\""),
+            None => format!("choice of {}", self.members.join("/")),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Menu {
     pub name: String,
     pub entries: Vec<Entry>,
+    /// Where the `menu "NAME"` line is, as a byte range in the text of the file it was parsed
+    /// from
+    pub span: Option<Range<usize>>,
 }
 
 impl Menu {
@@ -245,48 +1147,248 @@ impl Menu {
         Self {
             name: name.to_string(),
             entries: vec![],
+            span: None,
         }
     }
-}
 
-impl Menu {
-    fn pretty_format(&self, f: &mut fmt::Formatter, kconfig: &KConfig, depth: i32) -> fmt::Result {
-        if depth > 0 {
-            spaces(f, depth - 1)?;
-            writeln!(f, "menu \"{}\"", self.name)?;
-        }
-        for ent in &self.entries {
-            match ent {
-                Entry::Menu(m) => {
-                    m.pretty_format(f, kconfig, depth + 1)?;
-                }
-                Entry::Variable(s) => {
-                    let var = kconfig.vars.get(s);
-                    if let Some(var) = var {
-                        var.pretty_format(f, depth)?;
-                    }
-                }
-            }
-        }
-        if depth > 0 {
-            spaces(f, depth - 1)?;
-            writeln!(f, "endmenu\n")?;
-        }
-        Ok(())
+    /// The number of entries directly in this menu
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
     }
-}
 
-impl fmt::Display for Menu {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "menu \"{}\"", self.name)?;
-        for ent in &self.entries {
-            match ent {
-                Entry::Menu(m) => {
-                    m.fmt(f)?;
+    /// The number of entries in this menu and every menu under it. A submenu counts as an
+    /// entry as well as its contents, and a choice as well as its members.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     "config A\n bool\nmenu \"Outer\"\nconfig B\n bool\nmenu \"Inner\"\nconfig C\n bool\nendmenu\nendmenu\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.num_vars(), 3);
+    /// assert_eq!(kconfig.root.num_entries(), 2);
+    /// assert_eq!(kconfig.root.total_entries(), 5);
+    /// assert_eq!(kconfig.find_menu("Inner").unwrap().total_entries(), 1);
+    /// ```
+    pub fn total_entries(&self) -> usize {
+        let nested: usize = self
+            .entries
+            .iter()
+            .map(|ent| match ent {
+                Entry::Menu(m) => m.total_entries(),
+                Entry::Choice(c) => c.members.len(),
+                Entry::Variable(_) | Entry::SourceComment(_) => 0,
+            })
+            .sum();
+        self.entries.len() + nested
+    }
+
+    /// Find the menu called `name` in this menu's subtree, including this menu itself. If more
+    /// than one menu has that name, the first one in depth-first order is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{Entry, Menu};
+    ///
+    /// let mut inner = Menu::new("Inner");
+    /// inner.entries.push(Entry::Menu(Menu::new("Deepest")));
+    /// let mut outer = Menu::new("Outer");
+    /// outer.entries.push(Entry::Menu(inner));
+    ///
+    /// assert_eq!(outer.find("Deepest").unwrap().name, "Deepest");
+    /// assert!(outer.find("Missing").is_none());
+    /// ```
+    pub fn find(&self, name: &str) -> Option<&Menu> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.entries.iter().find_map(|ent| match ent {
+            Entry::Menu(m) => m.find(name),
+            _ => None,
+        })
+    }
+
+    /// Mutable version of [`find`](Menu::find)
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Menu> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.entries.iter_mut().find_map(|ent| match ent {
+            Entry::Menu(m) => m.find_mut(name),
+            _ => None,
+        })
+    }
+
+    /// Change every binding of the variable `old` in this menu's subtree into one of `new`
+    fn rebind(&mut self, old: &str, new: &str) {
+        for ent in &mut self.entries {
+            match ent {
+                Entry::Variable(s) if s == old => *s = new.to_string(),
+                Entry::Menu(m) => m.rebind(old, new),
+                Entry::Choice(c) => {
+                    for member in c.members.iter_mut().filter(|m| *m == old) {
+                        *member = new.to_string();
+                    }
+                    if c.default.as_deref() == Some(old) {
+                        c.default = Some(new.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Remove every binding of the variable `name` from this menu's subtree
+    fn unbind(&mut self, name: &str) {
+        self.entries.retain_mut(|ent| match ent {
+            Entry::Variable(s) => s != name,
+            Entry::Menu(m) => {
+                m.unbind(name);
+                true
+            }
+            Entry::Choice(c) => {
+                c.members.retain(|m| m != name);
+                if c.default.as_deref() == Some(name) {
+                    c.default = None;
+                }
+                true
+            }
+            Entry::SourceComment(_) => true,
+        });
+    }
+
+    /// Write the entries of this menu as an outline, for [`KConfig::menu_tree_string`]
+    fn outline(&self, kconfig: &KConfig, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        for ent in &self.entries {
+            match ent {
+                Entry::Menu(m) => {
+                    out.push_str(&format!("{indent}{}\n", m.name));
+                    m.outline(kconfig, depth + 1, out);
+                }
+                Entry::Variable(s) => {
+                    let Some(var) = kconfig.vars.get(s) else {
+                        continue;
+                    };
+                    let value = kconfig.effective_value(s);
+                    let ty = var.ty.or(value.as_ref().map(Value::type_of));
+                    let shown = match (ty, value) {
+                        (Some(Type::Bool) | None, Some(Value::Bool(true))) => "[*]".to_string(),
+                        (Some(Type::Bool) | None, _) => "[ ]".to_string(),
+                        (_, Some(v)) => format!("({v})"),
+                        (_, None) => "()".to_string(),
+                    };
+                    out.push_str(&format!("{indent}{shown} {s}\n"));
+                }
+                Entry::Choice(c) => {
+                    let prompt = c.prompt.as_deref().unwrap_or("choice");
+                    out.push_str(&format!("{indent}{prompt}\n"));
+                    for m in &c.members {
+                        let on = kconfig.effective_value(m) == Some(Value::Bool(true));
+                        let shown = if on { "(X)" } else { "( )" };
+                        out.push_str(&format!("{indent}  {shown} {m}\n"));
+                    }
+                }
+                Entry::SourceComment(_) => {}
+            }
+        }
+    }
+
+    /// Collect every choice in this menu and the menus under it
+    fn collect_choices<'a>(&'a self, out: &mut Vec<&'a Choice>) {
+        for ent in &self.entries {
+            match ent {
+                Entry::Menu(m) => m.collect_choices(out),
+                Entry::Choice(c) => out.push(c),
+                Entry::Variable(_) | Entry::SourceComment(_) => {}
+            }
+        }
+    }
+}
+
+impl Menu {
+    fn pretty_format(
+        &self,
+        f: &mut fmt::Formatter,
+        kconfig: &KConfig,
+        opts: &FormatOptions,
+        depth: i32,
+    ) -> fmt::Result {
+        if depth > 0 {
+            spaces(f, opts, depth - 1)?;
+            writeln!(f, "menu {}", quote(&self.name))?;
+        }
+        for ent in &self.entries {
+            match ent {
+                Entry::Menu(m) => {
+                    m.pretty_format(f, kconfig, opts, depth + 1)?;
+                }
+                Entry::Variable(s) => {
+                    let var = kconfig.vars.get(s);
+                    if let Some(var) = var {
+                        var.pretty_format(f, opts, depth)?;
+                    }
+                }
+                Entry::SourceComment(c) => {
+                    spaces(f, opts, depth)?;
+                    writeln!(f, "# {c}")?;
+                }
+                Entry::Choice(c) => {
+                    spaces(f, opts, depth)?;
+                    writeln!(f, "choice")?;
+                    if let Some(prompt) = &c.prompt {
+                        spaces(f, opts, depth + 1)?;
+                        writeln!(f, "prompt {}", quote(prompt))?;
+                    }
+                    if c.optional {
+                        spaces(f, opts, depth + 1)?;
+                        writeln!(f, "optional")?;
+                    }
+                    if let Some(default) = &c.default {
+                        spaces(f, opts, depth + 1)?;
+                        writeln!(f, "default {default}")?;
+                    }
+                    for var in c.members.iter().filter_map(|m| kconfig.vars.get(m)) {
+                        var.pretty_format(f, opts, depth + 1)?;
+                    }
+                    spaces(f, opts, depth)?;
+                    writeln!(f, "endchoice")?;
+                }
+            }
+        }
+        if depth > 0 {
+            spaces(f, opts, depth - 1)?;
+            writeln!(f, "endmenu\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Menu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "menu {}", quote(&self.name))?;
+        for ent in &self.entries {
+            match ent {
+                Entry::Menu(m) => {
+                    m.fmt(f)?;
                 }
                 Entry::Variable(s) => {
                     writeln!(f, "  {}", s)?;
                 }
+                Entry::SourceComment(c) => {
+                    writeln!(f, "  # {}", c)?;
+                }
+                Entry::Choice(c) => {
+                    writeln!(f, "  choice")?;
+                    for m in &c.members {
+                        writeln!(f, "    {}", m)?;
+                    }
+                    writeln!(f, "  endchoice")?;
+                }
             }
         }
         writeln!(f, "endmenu")?;
@@ -294,7 +1396,161 @@ impl fmt::Display for Menu {
     }
 }
 
-#[derive(Debug)]
+/// Variables are shown by name, comments with their `#`, and menus and choices as blocks
+/// listing what is in them
+///
+/// # Examples
+///
+/// ```
+/// use konf::{Choice, Entry, Menu};
+///
+/// assert_eq!(Entry::Variable("DEBUG".to_string()).to_string(), "DEBUG");
+/// assert_eq!(Entry::SourceComment("Debugging".to_string()).to_string(), "# Debugging");
+///
+/// let mut menu = Menu::new("Debugging");
+/// menu.entries.push(Entry::Variable("DEBUG".to_string()));
+/// assert_eq!(
+///     Entry::Menu(menu).to_string(),
+///     "menu \"Debugging\"\n  DEBUG\nendmenu\n"
+/// );
+///
+/// let choice = Choice {
+///     members: vec!["GCC".to_string(), "CLANG".to_string()],
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     Entry::Choice(choice).to_string(),
+///     "choice\n  GCC\n  CLANG\nendchoice\n"
+/// );
+/// ```
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entry::Variable(s) => f.write_str(s),
+            Entry::Menu(m) => m.fmt(f),
+            Entry::SourceComment(c) => write!(f, "# {}", c),
+            Entry::Choice(c) => {
+                writeln!(f, "choice")?;
+                for m in &c.members {
+                    writeln!(f, "  {}", m)?;
+                }
+                writeln!(f, "endchoice")
+            }
+        }
+    }
+}
+
+/// A tiny splitmix64 generator. `randconfig` only needs to be reproducible, not secure
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Pick a number in `min..=max`
+    fn range(&mut self, min: i128, max: i128) -> i128 {
+        if max <= min {
+            return min;
+        }
+        let span = (max - min + 1) as u128;
+        min + (self.next() as u128 % span) as i128
+    }
+}
+
+/// The iterator behind [`KConfig::iter_tree`], walking the menu tree depth-first
+struct TreeIter<'a> {
+    kconfig: &'a KConfig,
+    /// The menus being walked, outermost first, each with the index of its next entry
+    stack: Vec<(&'a Menu, usize)>,
+    /// The members left to visit of the choice being walked
+    members: std::slice::Iter<'a, String>,
+}
+
+impl<'a> TreeIter<'a> {
+    fn new(kconfig: &'a KConfig) -> Self {
+        Self {
+            kconfig,
+            stack: vec![(&kconfig.root, 0)],
+            members: [].iter(),
+        }
+    }
+
+    /// The next variable bound into the tree, without building its path
+    fn next_var(&mut self) -> Option<&'a Variable> {
+        loop {
+            if let Some(name) = self.members.next() {
+                match self.kconfig.vars.get(name) {
+                    Some(var) => return Some(var),
+                    None => continue,
+                }
+            }
+            let (menu, idx) = self.stack.last_mut()?;
+            let menu: &'a Menu = menu;
+            let Some(ent) = menu.entries.get(*idx) else {
+                self.stack.pop();
+                continue;
+            };
+            *idx += 1;
+            match ent {
+                Entry::Menu(m) => self.stack.push((m, 0)),
+                Entry::Variable(s) => {
+                    if let Some(var) = self.kconfig.vars.get(s) {
+                        return Some(var);
+                    }
+                }
+                Entry::Choice(c) => self.members = c.members.iter(),
+                Entry::SourceComment(_) => {}
+            }
+        }
+    }
+
+    /// The menus enclosing the variable last returned, starting at `root`
+    fn path(&self) -> Vec<&'a Menu> {
+        self.stack.iter().map(|(m, _)| *m).collect()
+    }
+}
+
+impl<'a> Iterator for TreeIter<'a> {
+    type Item = (Vec<&'a Menu>, &'a Variable);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let var = self.next_var()?;
+        Some((self.path(), var))
+    }
+}
+
+/// How [`KConfig::merge`] settles a symbol defined in both configurations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the definition already there
+    KeepExisting,
+    /// Replace it with the incoming definition
+    Overwrite,
+    /// Fail the merge
+    Error,
+}
+
+/// A parsed Kconfig tree, along with the current value of each config. Two `KConfig`s are equal
+/// when their names, menus and variables (including values) are
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, Value};
+///
+/// let kconfig = parser::parse_str("config JOBS\n int\n default 4\n").unwrap();
+/// let mut copy = kconfig.clone();
+/// assert_eq!(copy, kconfig);
+///
+/// copy.vars["JOBS"].value = Some(Value::Int(8));
+/// assert_ne!(copy, kconfig);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
 pub struct KConfig {
     pub name: String,
     pub root: Menu,
@@ -318,22 +1574,123 @@ impl KConfig {
         }
     }
 
-    /// Merge a kconfig into another. This is the implementation for the `source` operation in
-    /// Kconfig files. By consuming `other`, this method takes all variables and menu entries and
-    /// moves them into `self` appropriately
+    /// Start building a KConfig in code, without writing out each [`Variable`] and [`Entry`]
     ///
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
+    /// use konf::{KConfig, Type, Value};
+    ///
+    /// let kconfig = KConfig::builder()
+    ///     .var("DEBUG", Type::Bool, None)
+    ///     .menu("Build", |m| m.var("JOBS", Type::Int, Some(Value::Int(4))))
+    ///     .build();
+    /// assert_eq!(
+    ///     kconfig.to_string(),
+    ///     concat!(
+    ///         "mainmenu \"config\"\n",
+    ///         "\n",
+    ///         "config DEBUG\n",
+    ///         "    bool\n",
+    ///         "menu \"Build\"\n",
+    ///         "    config JOBS\n",
+    ///         "        int\n",
+    ///         "        default 4\n",
+    ///         "endmenu\n",
+    ///         "\n",
+    ///     )
+    /// );
+    /// ```
+    pub fn builder() -> Builder {
+        Builder {
+            menu: Menu::new("(top)"),
+            vars: IndexMap::new(),
+        }
+    }
+
+    /// Merge a kconfig into another, as the `source` operation in Kconfig files does. By
+    /// consuming `other`, this method takes all variables and menu entries and moves them into
+    /// `self`. This is [`merge`](KConfig::merge) with [`MergePolicy::Overwrite`], so a symbol
+    /// defined in both takes the definition from `other`.
+    ///
+    /// # Examples
     ///
-    /// let mut kconfig = ;
-    /// let mut other = ;
+    /// ```
+    /// use konf::{parser, KConfig, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut other = KConfig::new();
+    /// other.add_var(Variable::new("DEBUG"));
     /// kconfig.source(other);
+    /// assert!(kconfig.vars.contains_key("DEBUG"));
+    ///
+    /// // the menu tree comes along too
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\n").unwrap();
+    /// kconfig.source(parser::parse_str("menu \"Debug\"\nconfig GCC\n int\nendmenu\n").unwrap());
+    /// assert_eq!(kconfig.vars["GCC"].ty, Some(konf::Type::Int));
+    /// assert_eq!(kconfig.path_to("GCC").unwrap(), ["(top)"]);
+    /// assert!(kconfig.find_menu("Debug").is_some());
     /// ```
     pub fn source(&mut self, other: Self) {
-        // TODO: deal with the `root`
-        self.vars.extend(other.vars);
+        // overwriting never conflicts, so this can't fail
+        let _ = self.merge(other, MergePolicy::Overwrite);
+    }
+
+    /// Layer `other` on top of this configuration, adding its variables and appending its menu
+    /// tree to `root`. A symbol defined in both is a conflict, settled by `policy`; either way it
+    /// stays bound where it already was in this tree. Returns the names of the conflicting
+    /// symbols.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, MergePolicy, Value};
+    ///
+    /// let base = parser::parse_str("config JOBS\n int\n default 1\n").unwrap();
+    /// let overlay = parser::parse_str("config JOBS\n int\n default 8\nconfig LTO\n bool\n").unwrap();
+    ///
+    /// let mut kconfig = base.clone();
+    /// let conflicts = kconfig.merge(overlay.clone(), MergePolicy::KeepExisting).unwrap();
+    /// assert_eq!(conflicts, ["JOBS"]);
+    /// assert_eq!(kconfig.default_value("JOBS"), Some(Value::Int(1)));
+    /// assert!(kconfig.vars.contains_key("LTO"));
+    ///
+    /// let mut kconfig = base.clone();
+    /// kconfig.merge(overlay.clone(), MergePolicy::Overwrite).unwrap();
+    /// assert_eq!(kconfig.default_value("JOBS"), Some(Value::Int(8)));
+    /// assert_eq!(kconfig.iter_tree().count(), 2);
+    ///
+    /// let mut kconfig = base.clone();
+    /// let err = kconfig.merge(overlay, MergePolicy::Error).unwrap_err();
+    /// assert_eq!(err.to_string(), "symbol JOBS is defined in both configurations");
+    /// assert_eq!(kconfig, base);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// With [`MergePolicy::Error`], this function will return an error for the first conflict,
+    /// leaving this configuration unchanged.
+    pub fn merge(&mut self, mut other: Self, policy: MergePolicy) -> Result<Vec<String>> {
+        let conflicts: Vec<String> = other
+            .vars
+            .keys()
+            .filter(|k| self.vars.contains_key(*k))
+            .cloned()
+            .collect();
+        if let (MergePolicy::Error, Some(name)) = (policy, conflicts.first()) {
+            let msg = format!("symbol {name} is defined in both configurations");
+            return Err(Error::new(ErrorKind::Other, msg));
+        }
+        for name in &conflicts {
+            other.root.unbind(name);
+        }
+        for (name, var) in other.vars {
+            if policy == MergePolicy::Overwrite || !self.vars.contains_key(&name) {
+                self.vars.insert(name, var);
+            }
+        }
+        self.root.entries.extend(other.root.entries);
+        Ok(conflicts)
     }
 
     /// Add a variable to the KConfig. This does not result in a binding into a menu
@@ -341,120 +1698,2881 @@ impl KConfig {
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
+    /// use konf::{KConfig, Variable};
     ///
-    /// let mut kconfig = ;
-    /// kconfig.add_var(var);
+    /// let mut kconfig = KConfig::new();
+    /// kconfig.add_var(Variable::new("DEBUG"));
+    /// assert!(kconfig.vars.contains_key("DEBUG"));
+    /// assert!(kconfig.root.entries.is_empty());
     /// ```
     pub fn add_var(&mut self, var: Variable) {
         self.vars.insert(var.name.to_string(), var);
     }
 
-    /// Save the current value state of all variables in a KConfig
+    /// The number of variables defined, whether or not they are bound into the menu tree
     ///
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
-    /// let kconfig = ...;
-    /// let values = kconfig.save();
+    /// use konf::{KConfig, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// assert!(kconfig.is_empty());
+    /// kconfig.add_var(Variable::new("DEBUG"));
+    /// assert_eq!(kconfig.num_vars(), 1);
+    /// assert!(!kconfig.is_empty());
     /// ```
-    pub fn save(&self) -> IndexMap<String, Option<Value>> {
+    pub fn num_vars(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// Whether no variables are defined
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    /// The names of the variables, in declaration order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(8));
+    /// assert_eq!(kconfig.symbols().collect::<Vec<_>>(), ["GCC", "JOBS"]);
+    /// assert_eq!(
+    ///     kconfig.values().collect::<Vec<_>>(),
+    ///     [("GCC", None), ("JOBS", Some(&Value::Int(8)))]
+    /// );
+    /// ```
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.vars.keys().map(String::as_str)
+    }
+
+    /// The name and current value of each variable, in declaration order. Defaults aren't
+    /// filled in.
+    pub fn values(&self) -> impl Iterator<Item = (&str, Option<&Value>)> {
         self.vars
             .iter()
-            .map(|(k, v)| (k.clone(), v.value.clone()))
-            .collect()
+            .map(|(k, v)| (k.as_str(), v.value.as_ref()))
     }
 
-    /// Save the KConfig's current value state to a .config file located at `config`
+    /// The help text of the variable `name`, or `None` if it has none or doesn't exist
     ///
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     "config SMP\n bool\n help\n  Run on more than one CPU.\nconfig NUMA\n bool\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.help_for("SMP"), Some("Run on more than one CPU."));
+    /// assert_eq!(kconfig.help_for("NUMA"), None);
+    /// assert_eq!(kconfig.help_for("MISSING"), None);
+    /// ```
+    pub fn help_for(&self, name: &str) -> Option<&str> {
+        self.vars.get(name)?.help.as_deref()
+    }
+
+    /// Remove the variable `name`, along with every binding of it in the menu tree, returning it
+    /// if it existed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Entry};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Drivers"
+    ///         config OLD_DRIVER
+    ///             bool
+    ///         config NEW_DRIVER
+    ///             bool
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// // bind it a second time, at the top level
+    /// kconfig.root.entries.push(Entry::Variable("OLD_DRIVER".to_string()));
     ///
-    /// let kconfig = ;
-    /// kconfig.save_config(".config")
+    /// let var = kconfig.remove_var("OLD_DRIVER").unwrap();
+    /// assert_eq!(var.name, "OLD_DRIVER");
+    /// assert!(!kconfig.vars.contains_key("OLD_DRIVER"));
+    /// assert_eq!(kconfig.path_to("OLD_DRIVER"), None);
+    /// assert_eq!(kconfig.root.entries.len(), 1);
+    /// assert_eq!(kconfig.find_menu("Drivers").unwrap().entries.len(), 1);
+    ///
+    /// assert!(kconfig.remove_var("OLD_DRIVER").is_none());
+    /// ```
+    pub fn remove_var(&mut self, name: &str) -> Option<Variable> {
+        let var = self.vars.shift_remove(name)?;
+        self.root.unbind(name);
+        Some(var)
+    }
+
+    /// Rename the variable `old` to `new`, updating its bindings in the menu tree and every
+    /// reference to it from other variables: `depends on` expressions, defaults and their
+    /// conditions, and `select`s and `imply`s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Entry};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Networking"
+    ///         config NET_OLD
+    ///             bool
+    ///     endmenu
+    ///     config WIFI
+    ///         bool
+    ///         depends on NET_OLD && !EMBEDDED
+    ///         default NET_OLD
+    ///         select NET_OLD if NET_OLD
+    ///     config EMBEDDED
+    ///         bool
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.rename_var("NET_OLD", "NET").unwrap();
+    ///
+    /// assert!(!kconfig.vars.contains_key("NET_OLD"));
+    /// assert_eq!(kconfig.vars["NET"].name, "NET");
+    /// assert_eq!(kconfig.vars.keys().collect::<Vec<_>>(), ["NET", "WIFI", "EMBEDDED"]);
+    /// assert_eq!(kconfig.path_to("NET").unwrap(), ["(top)", "Networking"]);
+    ///
+    /// let wifi = &kconfig.vars["WIFI"];
+    /// assert_eq!(wifi.depends.as_ref().unwrap().to_string(), "NET && !EMBEDDED");
+    /// assert_eq!(wifi.defaults[0].0.as_symbol(), Some("NET"));
+    /// assert_eq!(wifi.selects[0].0, "NET");
+    /// assert_eq!(wifi.selects[0].1.as_ref().unwrap().to_string(), "NET");
+    ///
+    /// assert!(kconfig.rename_var("NET", "WIFI").is_err());
+    /// assert!(kconfig.rename_var("MISSING", "OTHER").is_err());
+    /// ```
+    ///
+    /// A choice's `default` follows its member being renamed:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "choice\n prompt \"cc\"\n default CLANG\nconfig GCC\n bool\nconfig CLANG\n bool\nendchoice\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.rename_var("CLANG", "LLVM").unwrap();
+    /// assert_eq!(kconfig.choices()[0].default.as_deref(), Some("LLVM"));
+    ///
+    /// kconfig.load_default();
+    /// assert_eq!(kconfig.vars["LLVM"].value, Some(Value::Bool(true)));
+    /// assert!(kconfig.validate().is_ok());
+    ///
+    /// // and is cleared when it is removed
+    /// kconfig.remove_var("LLVM");
+    /// assert_eq!(kconfig.choices()[0].default, None);
     /// ```
     ///
     /// # Errors
     ///
-    /// This function will return an error if it cannot create the file or cannot write to the
-    /// file.
-    pub fn save_config(&self, config: &str) -> io::Result<()> {
-        let mut file = std::fs::File::create(config)?;
-        let settings = self.save();
-        for (k, v) in &settings {
-            match v {
-                Some(v) => {
-                    match v {
-                        Value::Bool(false) => writeln!(&mut file, "# CONFIG_{k} is not set")?,
-                        _ => writeln!(&mut file, "CONFIG_{k}={v}")?,
-                    }
-                    //
-                }
-                None => {
-                    writeln!(&mut file, "# CONFIG_{k} is not set")?;
-                    //
-                }
-            };
+    /// This function will return an error if there is no variable called `old`, or if there
+    /// already is one called `new`.
+    pub fn rename_var(&mut self, old: &str, new: &str) -> Result<()> {
+        if !self.vars.contains_key(old) {
+            return Err(Error::new(
+                ErrorKind::UnknownSymbol,
+                format!("unknown symbol {old}"),
+            ));
         }
-
+        if self.vars.contains_key(new) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("symbol {new} already exists"),
+            ));
+        }
+        self.vars = std::mem::take(&mut self.vars)
+            .into_iter()
+            .map(|(k, mut var)| {
+                var.rename_references(old, new);
+                if k == old {
+                    var.name = new.to_string();
+                    (new.to_string(), var)
+                } else {
+                    (k, var)
+                }
+            })
+            .collect();
+        self.root.rebind(old, new);
         Ok(())
     }
 
-    /// Load the default configuration from the `default` values
+    /// The current value of the variable `name`: `y` if it is [forced](KConfig::is_forced) by a
+    /// `select`, otherwise its value if it has one, and otherwise its default. This is
+    /// [`effective_value`](KConfig::effective_value), except for forcing and that a symbol
+    /// which doesn't exist is an error rather than `None`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
+    /// use konf::{parser, ErrorKind, Value};
     ///
-    /// let mut kconfig = ;
-    /// kconfig.load_default();
+    /// let kconfig = parser::parse_str("config JOBS\n int\n default 4\nconfig CC\n string\n").unwrap();
+    /// assert_eq!(kconfig.get("JOBS"), Ok(Some(Value::Int(4))));
+    /// assert_eq!(kconfig.get("CC"), Ok(None));
+    /// assert_eq!(kconfig.get("MISSING").unwrap_err().kind(), ErrorKind::UnknownSymbol);
+    ///
+    /// let mut kconfig = parser::parse_str("config A\n bool\n select B\nconfig B\n bool\n").unwrap();
+    /// kconfig.set("A", Value::Bool(true)).unwrap();
+    /// kconfig.evaluate().unwrap();
+    /// kconfig.set("B", Value::Bool(false)).unwrap();
+    /// assert_eq!(kconfig.get("B"), Ok(Some(Value::Bool(true))));
+    /// assert!(kconfig.is_forced("B"));
     /// ```
-    pub fn load_default(&mut self) {
-        for (_k, v) in &mut self.vars {
-            v.value = v.default.clone();
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `name`.
+    pub fn get(&self, name: &str) -> Result<Option<Value>> {
+        if !self.vars.contains_key(name) {
+            let msg = format!("unknown symbol {name}");
+            return Err(Error::new(ErrorKind::UnknownSymbol, msg));
         }
+        if self.is_forced(name) {
+            return Ok(Some(Value::Bool(true)));
+        }
+        Ok(self.effective_value(name))
     }
 
-    /// Load a `.config` file located at `config_file` into the KConfig's state
+    /// Whether the variable `name` is forced to `y` by a `select` from a visible variable which
+    /// is `y`, so that a front end should not let it be changed. An `imply` doesn't force.
     ///
     /// # Examples
     ///
     /// ```
-    /// use konf::KConfig;
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n bool\n select B\n imply C\nconfig B\n bool\nconfig C\n bool\n",
+    /// )
+    /// .unwrap();
+    /// assert!(!kconfig.is_forced("B"));
+    /// kconfig.set("A", Value::Bool(true)).unwrap();
+    /// assert!(kconfig.is_forced("B"));
+    /// assert!(!kconfig.is_forced("C"));
+    /// ```
+    pub fn is_forced(&self, name: &str) -> bool {
+        self.triggered(name, |var| &var.selects)
+    }
+
+    /// Set the value of the variable `name`, checking that `value` is of the variable's type,
+    /// and within its `range` if it has one. See [`ErrorKind`] for telling apart what went
+    /// wrong.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config JOBS\n int\n").unwrap();
+    /// kconfig.set("JOBS", Value::Int(8)).unwrap();
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(8)));
     ///
-    /// let mut kconfig = ;
-    /// assert_eq!(kconfig.load(config_file), );
-    /// assert_eq!(kconfig, );
+    /// let err = kconfig.set("JOBS", Value::String("eight".to_string())).unwrap_err();
+    /// assert_eq!(err.to_string(), "symbol JOBS expects int but got string");
+    /// assert!(kconfig.set("MISSING", Value::Int(8)).is_err());
     /// ```
     ///
     /// # Errors
     ///
-    /// This function will return an error if the file at `config_file` is not found, or it is
-    /// malformed in any way.
-    pub fn load(&mut self, config_file: &str) -> io::Result<()> {
-        let file = std::fs::File::open(config_file)?;
-        let reader = BufReader::new(file);
-        for line in reader.lines() {
-            let res = parser::parse_config_line(&line?);
-            if let Some((k, v)) = res {
-                if let Some(var) = self.vars.get_mut(&k) {
-                    var.value = Some(v);
-                }
-            }
-        }
+    /// This function will return an error if there is no variable called `name`, or if `value`
+    /// is of the wrong type for it or out of its range. The variable is left unchanged.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<()> {
+        let var = self.vars.get(name).ok_or_else(|| {
+            Error::new(ErrorKind::UnknownSymbol, format!("unknown symbol {name}"))
+        })?;
+        var.check_type(&value)?;
+        self.check_range(var, &value)?;
+        self.vars[name].value = Some(value);
         Ok(())
     }
-}
 
-impl fmt::Display for KConfig {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "mainmenu \"{}\"", self.name)?;
-        f.write_str("\n")?;
-        self.root.pretty_format(f, self, 0)?;
+    /// Set several variables at once. Every change is checked as [`set`](KConfig::set) would
+    /// before any are applied, so either all of them are made or none are. Ranges which refer to
+    /// symbols are checked against the values from before the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, ErrorKind, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config GCC\n bool\nconfig JOBS\n int\n range 1 64\nconfig CC\n string\n",
+    /// )
+    /// .unwrap();
+    /// let before = kconfig.clone();
+    /// let changes = [
+    ///     ("GCC".to_string(), Value::Bool(true)),
+    ///     ("JOBS".to_string(), Value::Int(100)),
+    ///     ("CC".to_string(), Value::String("gcc".to_string())),
+    /// ];
+    /// let err = kconfig.set_many(&changes).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    /// assert_eq!(kconfig, before);
+    ///
+    /// let changes = [("GCC".to_string(), Value::Bool(true)), ("JOBS".to_string(), Value::Int(8))];
+    /// kconfig.set_many(&changes).unwrap();
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(8)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for the first change naming an unknown variable, or
+    /// with a value of the wrong type or out of range, leaving every variable unchanged.
+    pub fn set_many(&mut self, changes: &[(String, Value)]) -> Result<()> {
+        for (name, value) in changes {
+            let var = self.vars.get(name).ok_or_else(|| {
+                Error::new(ErrorKind::UnknownSymbol, format!("unknown symbol {name}"))
+            })?;
+            var.check_type(value)?;
+            self.check_range(var, value)?;
+        }
+        for (name, value) in changes {
+            self.vars[name].value = Some(value.clone());
+        }
+        Ok(())
+    }
+
+    /// Set the value of the variable `name` from text typed in by a user, as a `menuconfig`
+    /// style front end would. The text is read as the variable's type with
+    /// [`Value::parse_as`], and must be within its `range`. Errors name the variable by its
+    /// prompt, so they can be shown to the user as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config DEBUG
+    ///         bool "Enable debugging"
+    ///     config NR_CPUS
+    ///         int "Maximum number of CPUs"
+    ///         range 1 64
+    ///     "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// kconfig.set_from_input("DEBUG", "y").unwrap();
+    /// assert_eq!(kconfig.vars["DEBUG"].value, Some(Value::Bool(true)));
+    /// let err = kconfig.set_from_input("DEBUG", "maybe").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Enable debugging (DEBUG): `maybe` is not a valid bool value"
+    /// );
+    ///
+    /// kconfig.set_from_input("NR_CPUS", "64").unwrap();
+    /// let err = kconfig.set_from_input("NR_CPUS", "65").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Maximum number of CPUs (NR_CPUS): 65 is not in the range 1 to 64"
+    /// );
+    /// assert_eq!(kconfig.vars["NR_CPUS"].value, Some(Value::Int(64)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `name`, or if `raw`
+    /// isn't a valid value for it. The variable is left unchanged.
+    pub fn set_from_input(&mut self, name: &str, raw: &str) -> Result<()> {
+        let var = self.vars.get(name).ok_or_else(|| {
+            Error::new(ErrorKind::UnknownSymbol, format!("unknown symbol {name}"))
+        })?;
+        let label = match &var.desc {
+            Some(desc) => format!("{desc} ({name})"),
+            None => name.to_string(),
+        };
+        let value =
+            Value::parse_as(raw, var.ty.unwrap_or_default()).map_err(|e| e.context(&label))?;
+        if let Some(msg) = self.out_of_range(var, &value)? {
+            return Err(Error::new(ErrorKind::OutOfRange, msg).context(label));
+        }
+        self.vars[name].value = Some(value);
+        Ok(())
+    }
+
+    /// Set every variable named in `map` to the value given for it, as saved by
+    /// [`save`](KConfig::save). `None` unsets a variable. Names with no variable are skipped and
+    /// returned. Every value is checked as [`set`](KConfig::set) would before any are applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(8));
+    /// let before = kconfig.clone();
+    /// assert!(kconfig.apply_map(&kconfig.save()).unwrap().is_empty());
+    /// assert_eq!(kconfig, before);
+    ///
+    /// let mut map = kconfig.save();
+    /// map.insert("GCC".to_string(), Some(Value::Bool(true)));
+    /// map.insert("MISSING".to_string(), Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.apply_map(&map).unwrap(), ["MISSING"]);
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(true)));
+    ///
+    /// map.insert("GCC".to_string(), Some(Value::Bool(false)));
+    /// map.insert("JOBS".to_string(), Some(Value::Bool(true)));
+    /// let err = kconfig.apply_map(&map).unwrap_err();
+    /// assert_eq!(err.to_string(), "symbol JOBS expects int but got bool");
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(true)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for the first value which is of the wrong type for
+    /// its variable or out of its range, leaving every variable unchanged.
+    pub fn apply_map(&mut self, map: &IndexMap<String, Option<Value>>) -> Result<Vec<String>> {
+        let mut unknown = vec![];
+        for (name, value) in map {
+            match (self.vars.get(name), value) {
+                (None, _) => unknown.push(name.clone()),
+                (Some(var), Some(value)) => {
+                    var.check_type(value)?;
+                    self.check_range(var, value)?;
+                }
+                (Some(_), None) => {}
+            }
+        }
+        for (name, value) in map {
+            if let Some(var) = self.vars.get_mut(name) {
+                var.value = value.clone();
+            }
+        }
+        Ok(unknown)
+    }
+
+    /// Unset the variable `name`, so that it falls back to its default
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\n default y\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.vars["GCC"].value = Some(Value::Bool(false));
+    /// kconfig.clear_value("GCC").unwrap();
+    /// assert_eq!(kconfig.vars["GCC"].value, None);
+    /// assert_eq!(kconfig.effective_value("GCC"), Some(Value::Bool(true)));
+    ///
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(8));
+    /// kconfig.clear_value("JOBS").unwrap();
+    /// assert_eq!(kconfig.effective_value("JOBS"), None);
+    /// assert!(kconfig.clear_value("MISSING").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `name`.
+    pub fn clear_value(&mut self, name: &str) -> Result<()> {
+        let var = self.vars.get_mut(name).ok_or_else(|| {
+            Error::new(ErrorKind::UnknownSymbol, format!("unknown symbol {name}"))
+        })?;
+        var.value = None;
+        Ok(())
+    }
+
+    /// Set the variable `name` to its default, or unset it if it has none
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\n default y\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.vars["GCC"].value = Some(Value::Bool(false));
+    /// kconfig.set_default("GCC").unwrap();
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(true)));
+    ///
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(8));
+    /// kconfig.set_default("JOBS").unwrap();
+    /// assert_eq!(kconfig.vars["JOBS"].value, None);
+    /// assert!(kconfig.set_default("MISSING").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `name`.
+    pub fn set_default(&mut self, name: &str) -> Result<()> {
+        if !self.vars.contains_key(name) {
+            let msg = format!("unknown symbol {name}");
+            return Err(Error::new(ErrorKind::UnknownSymbol, msg));
+        }
+        self.vars[name].value = self.default_value(name);
+        Ok(())
+    }
+
+    /// The `range` of `var` with any symbol ends replaced by their current values. Ends written
+    /// as an int for a hex symbol, or the other way around, are converted to its type.
+    fn range_of(&self, var: &Variable) -> Result<Option<(Value, Value)>> {
+        let Some((min, max)) = &var.range else {
+            return Ok(None);
+        };
+        let resolve = |end: &DefaultKind| match end {
+            DefaultKind::Literal(v) => Ok(var.coerce(v.clone()).unwrap_or_else(|_| v.clone())),
+            DefaultKind::Symbol(s) => match self.effective_value(s) {
+                Some(v @ (Value::Int(_) | Value::Hex(_))) => Ok(v),
+                _ => {
+                    let msg = format!("range of {} refers to {s}, which is not a number", var.name);
+                    Err(Error::new(ErrorKind::TypeMismatch, msg))
+                }
+            },
+        };
+        Ok(Some((resolve(min)?, resolve(max)?)))
+    }
+
+    /// Check that `value` is within the `range` of `var`, if it has one
+    fn check_range(&self, var: &Variable, value: &Value) -> Result<()> {
+        match self.out_of_range(var, value)? {
+            Some(msg) => {
+                let msg = format!("{}: {msg}", var.name);
+                Err(Error::new(ErrorKind::OutOfRange, msg))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Describe how `value` is outside the `range` of `var`, or `None` if it is within it
+    fn out_of_range(&self, var: &Variable, value: &Value) -> Result<Option<String>> {
+        let Some((min, max)) = self.range_of(var)? else {
+            return Ok(None);
+        };
+        // ints and hex values are both just numbers here
+        let cmp = |a: &Value, b: &Value| match (a.numeric(), b.numeric()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.partial_cmp(b),
+        };
+        let in_range = match (cmp(&min, value), cmp(value, &max)) {
+            (Some(lo), Some(hi)) => lo.is_le() && hi.is_le(),
+            // a value of another type is for `check_type` to complain about
+            _ => true,
+        };
+        if in_range {
+            Ok(None)
+        } else {
+            Ok(Some(format!("{value} is not in the range {min} to {max}")))
+        }
+    }
+
+    /// Check that the value of every variable is of the variable's type and within its `range`,
+    /// and that every choice has exactly one member set to `y`, or at most one if it is
+    /// `optional`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config DEBUG\n bool\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.vars["DEBUG"].value = Some(Value::Bool(true));
+    /// assert!(kconfig.validate().is_ok());
+    ///
+    /// kconfig.vars["JOBS"].value = Some(Value::Bool(true));
+    /// let err = kconfig.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "symbol JOBS expects int but got bool");
+    /// ```
+    ///
+    /// Ints and hex values compare as numbers, whichever way the ends of a `range` are written:
+    ///
+    /// ```
+    /// use konf::{parser, ErrorKind, Value};
+    ///
+    /// let mut kconfig = parser::parse_str("config JOBS\n int\n range 0 0x10\n").unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(16));
+    /// assert!(kconfig.validate().is_ok());
+    ///
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(20));
+    /// let err = kconfig.validate().unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    /// assert_eq!(err.to_string(), "JOBS: 20 is not in the range 0 to 16");
+    ///
+    /// let mut kconfig = parser::parse_str("config BASE\n hex\n range 4096 0xffff\n").unwrap();
+    /// assert!(kconfig.set("BASE", Value::from(0x100u64)).is_err());
+    /// assert!(kconfig.set("BASE", Value::from(0x1000u64)).is_ok());
+    /// ```
+    ///
+    /// An optional choice may be left with nothing chosen, but a required one may not:
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let src = "
+    /// choice
+    ///     prompt \"Compression\"
+    ///     optional
+    /// config GZIP
+    ///     bool
+    /// config XZ
+    ///     bool
+    /// endchoice
+    /// choice
+    ///     prompt \"Compiler\"
+    /// config GCC
+    ///     bool
+    /// config CLANG
+    ///     bool
+    /// endchoice
+    /// ";
+    /// let mut kconfig = parser::parse_str(src).unwrap();
+    /// let err = kconfig.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "no member of choice \"Compiler\" is selected");
+    ///
+    /// kconfig.select_choice("CLANG").unwrap();
+    /// assert!(kconfig.validate().is_ok());
+    /// ```
+    ///
+    /// The ends of a `range` can be other symbols:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let src = "config MAX\n int\n default 10\nconfig JOBS\n int\n range 0 MAX\n";
+    /// let mut kconfig = parser::parse_str(src).unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(10));
+    /// assert!(kconfig.validate().is_ok());
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(11));
+    /// let err = kconfig.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "JOBS: 11 is not in the range 0 to 10");
+    ///
+    /// let src = "config SMP\n bool\n default y\nconfig JOBS\n int\n range 0 SMP\n";
+    /// let mut kconfig = parser::parse_str(src).unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(1));
+    /// let err = kconfig.validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "range of JOBS refers to SMP, which is not a number");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error for the first variable holding a value of the wrong
+    /// type or out of its range, for a `range` referring to a symbol which isn't a number, or
+    /// for the first choice with the wrong number of members selected.
+    pub fn validate(&self) -> Result<()> {
+        for var in self.vars.values() {
+            if let Some(v) = &var.value {
+                var.check_type(v)?;
+            }
+        }
+        for var in self.vars.values() {
+            if let Some(v) = &var.value {
+                self.check_range(var, v)?;
+            }
+        }
+        for choice in self.choices() {
+            let on: Vec<&str> = choice
+                .members
+                .iter()
+                .filter(|m| self.effective_value(m) == Some(Value::Bool(true)))
+                .map(String::as_str)
+                .collect();
+            if on.len() > 1 {
+                let msg = format!(
+                    "more than one member of {} is selected: {}",
+                    choice.describe(),
+                    on.join(", ")
+                );
+                return Err(Error::new(ErrorKind::Other, msg));
+            }
+            if on.is_empty() && !choice.optional {
+                let msg = format!("no member of {} is selected", choice.describe());
+                return Err(Error::new(ErrorKind::Other, msg));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every `choice` in the menu tree, in declaration order
+    pub fn choices(&self) -> Vec<&Choice> {
+        let mut out = vec![];
+        self.root.collect_choices(&mut out);
+        out
+    }
+
+    /// The choice which `member` belongs to, as an error if it isn't in one
+    fn choice_of(&self, member: &str) -> Result<&Choice> {
+        if !self.vars.contains_key(member) {
+            let msg = format!("unknown symbol {member}");
+            return Err(Error::new(ErrorKind::UnknownSymbol, msg));
+        }
+        self.choices()
+            .into_iter()
+            .find(|c| c.members.iter().any(|m| m == member))
+            .ok_or_else(|| Error::new(ErrorKind::Other, format!("{member} is not in a choice")))
+    }
+
+    /// Choose `member` in its choice, setting it to `y` and every other member to `n`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let src = "choice\nconfig GCC\n bool\nconfig CLANG\n bool\nendchoice\n";
+    /// let mut kconfig = parser::parse_str(src).unwrap();
+    /// kconfig.select_choice("GCC").unwrap();
+    /// kconfig.select_choice("CLANG").unwrap();
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(false)));
+    /// assert_eq!(kconfig.vars["CLANG"].value, Some(Value::Bool(true)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `member`, or if it
+    /// isn't a member of a choice.
+    pub fn select_choice(&mut self, member: &str) -> Result<()> {
+        let members = self.choice_of(member)?.members.clone();
+        for m in members {
+            if let Some(var) = self.vars.get_mut(&m) {
+                var.value = Some(Value::Bool(m == member));
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn off every member of the choice `member` belongs to
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no variable called `member`, if it isn't
+    /// a member of a choice, or if the choice isn't `optional`.
+    pub fn clear_choice(&mut self, member: &str) -> Result<()> {
+        let choice = self.choice_of(member)?;
+        if !choice.optional {
+            let msg = format!("{} is not optional", choice.describe());
+            return Err(Error::new(ErrorKind::Other, msg));
+        }
+        let members = choice.members.clone();
+        for m in members {
+            if let Some(var) = self.vars.get_mut(&m) {
+                var.value = Some(Value::Bool(false));
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the current value state of all variables in a KConfig. Unset variables are `None`,
+    /// even if they have a default; see [`save_effective`](KConfig::save_effective).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut var = Variable::new("DEBUG");
+    /// var.value = Some(Value::Bool(true));
+    /// kconfig.add_var(var);
+    /// let values = kconfig.save();
+    /// assert_eq!(values["DEBUG"], Some(Value::Bool(true)));
+    /// ```
+    pub fn save(&self) -> IndexMap<String, Option<Value>> {
+        self.vars
+            .iter()
+            .map(|(k, v)| (k.clone(), v.value.clone()))
+            .collect()
+    }
+
+    /// Save the effective value of every variable, as [`save`](KConfig::save) does but with
+    /// unset variables falling back to their default
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config GCC\n bool\n default y\nconfig JOBS\n int\nconfig LTO\n bool\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.vars["LTO"].value = Some(Value::Bool(true));
+    ///
+    /// let values = kconfig.save_effective();
+    /// assert_eq!(values["GCC"], Some(Value::Bool(true)));
+    /// assert_eq!(values["JOBS"], None);
+    /// assert_eq!(values["LTO"], Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.save()["GCC"], None);
+    /// ```
+    pub fn save_effective(&self) -> IndexMap<String, Option<Value>> {
+        self.vars
+            .keys()
+            .map(|k| (k.clone(), self.effective_value(k)))
+            .collect()
+    }
+
+    /// Save the KConfig's current value state to a .config file located at `config`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut var = Variable::new("DEBUG");
+    /// var.value = Some(Value::Bool(true));
+    /// kconfig.add_var(var);
+    ///
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("konf-save-config-example-{}", std::process::id()));
+    /// kconfig.save_config(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(std::fs::read_to_string(&path).unwrap(), "CONFIG_DEBUG=y\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if it cannot create the file or cannot write to the
+    /// file.
+    pub fn save_config(&self, config: &str) -> io::Result<()> {
+        let mut file = std::fs::File::create(config)?;
+        self.save_config_to(&mut file)
+    }
+
+    /// Write the KConfig's current value state in `.config` format to any writer. Variables
+    /// which aren't [`visible`](KConfig::visible) are written with their default, whatever their
+    /// value, as [`normalize`](KConfig::normalize) would leave them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut gcc = Variable::new("GCC");
+    /// gcc.value = Some(Value::Bool(true));
+    /// kconfig.add_var(gcc);
+    /// kconfig.add_var(Variable::new("CLANG"));
+    /// let mut jobs = Variable::new("JOBS");
+    /// jobs.value = Some(Value::Int(4));
+    /// kconfig.add_var(jobs);
+    ///
+    /// let mut out = Vec::new();
+    /// kconfig.save_config_to(&mut out).unwrap();
+    /// assert_eq!(out, b"CONFIG_GCC=y\n# CONFIG_CLANG is not set\nCONFIG_JOBS=4\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to `w` fails.
+    pub fn save_config_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let settings = self.save();
+        for (k, v) in settings {
+            let v = if self.visible(&k) {
+                v
+            } else {
+                self.default_value(&k)
+            };
+            match &v {
+                Some(Value::Bool(false)) | None => writeln!(w, "# CONFIG_{k} is not set")?,
+                Some(v) => writeln!(w, "CONFIG_{k}={}", config_value(v))?,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Render the KConfig's current value state as the `.config` text [`save_config`] would
+    /// write
+    ///
+    /// [`save_config`]: KConfig::save_config
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut gcc = Variable::new("GCC");
+    /// gcc.value = Some(Value::Bool(true));
+    /// kconfig.add_var(gcc);
+    /// kconfig.add_var(Variable::new("CLANG"));
+    ///
+    /// let text = kconfig.save_config_string();
+    /// assert_eq!(text, "CONFIG_GCC=y\n# CONFIG_CLANG is not set\n");
+    ///
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("konf-save-config-string-example-{}", std::process::id()));
+    /// kconfig.save_config(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(std::fs::read(&path).unwrap(), text.as_bytes());
+    /// ```
+    ///
+    /// Quotes and backslashes in strings are escaped, so they read back unchanged:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config CC\n string \"Say \\\"hi\\\"\"\n default \"a\\\"b\"\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.vars["CC"].desc.as_deref(), Some("Say \"hi\""));
+    /// kconfig.load_default();
+    /// assert_eq!(kconfig.vars["CC"].value, Some(Value::String("a\"b".to_string())));
+    ///
+    /// let text = kconfig.save_config_string();
+    /// assert_eq!(text, "CONFIG_CC=\"a\\\"b\"\n");
+    /// kconfig.vars["CC"].value = Some(Value::String(r"C:\gcc\".to_string()));
+    /// let text = kconfig.save_config_string();
+    /// let mut again = kconfig.clone();
+    /// again.load_from(text.as_bytes()).unwrap();
+    /// assert_eq!(again.vars["CC"].value, kconfig.vars["CC"].value);
+    ///
+    /// // and the Kconfig itself survives being written out
+    /// let reparsed = parser::parse_str(&kconfig.to_string()).unwrap();
+    /// assert_eq!(reparsed.vars["CC"].desc, kconfig.vars["CC"].desc);
+    /// assert_eq!(reparsed.vars["CC"].defaults, kconfig.vars["CC"].defaults);
+    /// ```
+    pub fn save_config_string(&self) -> String {
+        let mut out = Vec::new();
+        // writing into a `Vec` can't fail, and we only ever write valid UTF-8
+        self.save_config_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Save a minimal `defconfig` to the file at `defconfig`, holding only the variables whose
+    /// value differs from their default. A variable without any default is written whenever it
+    /// is set. Loading the result with [`load_defconfig`](KConfig::load_defconfig) gives back
+    /// the same configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config SMP
+    ///         bool
+    ///         default y
+    ///     config NR_CPUS
+    ///         int
+    ///         default 8
+    ///     config DEBUG
+    ///         bool
+    ///     config CC
+    ///         string
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.load_default();
+    /// kconfig.vars["NR_CPUS"].value = Some(Value::Int(64));
+    /// kconfig.vars["CC"].value = Some(Value::String("clang".to_string()));
+    ///
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("konf-save-defconfig-example-{}", std::process::id()));
+    /// kconfig.save_defconfig(path.to_str().unwrap()).unwrap();
+    /// let defconfig = std::fs::read_to_string(&path).unwrap();
+    /// assert_eq!(defconfig, "CONFIG_NR_CPUS=64\nCONFIG_CC=\"clang\"\n");
+    ///
+    /// // the full config has everything, including values left at their default
+    /// let config = kconfig.save_config_string();
+    /// assert!(config.contains("CONFIG_SMP=y\n"));
+    /// assert!(!defconfig.contains("SMP"));
+    /// assert!(!defconfig.contains("DEBUG"));
+    ///
+    /// let mut reloaded = kconfig.clone();
+    /// reloaded.load_defconfig(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(reloaded, kconfig);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if it cannot create the file or cannot write to the
+    /// file.
+    pub fn save_defconfig(&self, defconfig: &str) -> io::Result<()> {
+        let mut file = std::fs::File::create(defconfig)?;
+        self.save_defconfig_to(&mut file)
+    }
+
+    /// Write a minimal `defconfig`, as [`save_defconfig`](KConfig::save_defconfig) does, to any
+    /// writer
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to `w` fails.
+    pub fn save_defconfig_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for (k, var) in &self.vars {
+            if !self.visible(k) {
+                continue;
+            }
+            let default = self.default_value(k);
+            let v = match (&var.value, &default) {
+                (None, _) => continue,
+                (Some(v), Some(d)) if v == d => continue,
+                (Some(v), _) => v,
+            };
+            match v {
+                Value::Bool(false) => writeln!(w, "# CONFIG_{k} is not set")?,
+                v => writeln!(w, "CONFIG_{k}={}", config_value(v))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The visible variables which are set to something other than `n`, with their values
+    fn enabled(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.vars.iter().filter_map(|(k, var)| match &var.value {
+            Some(Value::Bool(false)) | None => None,
+            Some(v) if self.visible(k) => Some((k.as_str(), v)),
+            Some(_) => None,
+        })
+    }
+
+    /// Write the KConfig out as Kconfig text, as its `Display` does, but laid out by `options`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, FormatOptions};
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     "mainmenu \"Example\"\nmenu \"Build\"\nconfig JOBS\nint\ndefault 4\nendmenu\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.format_with(&FormatOptions::default()), kconfig.to_string());
+    ///
+    /// let options = FormatOptions { indent: "  ".to_string() };
+    /// assert_eq!(
+    ///     kconfig.format_with(&options),
+    ///     concat!(
+    ///         "mainmenu \"Example\"\n",
+    ///         "\n",
+    ///         "menu \"Build\"\n",
+    ///         "  config JOBS\n",
+    ///         "    int\n",
+    ///         "    default 4\n",
+    ///         "endmenu\n",
+    ///         "\n",
+    ///     )
+    /// );
+    ///
+    /// let options = FormatOptions { indent: "\t".to_string() };
+    /// assert_eq!(
+    ///     kconfig.format_with(&options),
+    ///     concat!(
+    ///         "mainmenu \"Example\"\n",
+    ///         "\n",
+    ///         "menu \"Build\"\n",
+    ///         "\tconfig JOBS\n",
+    ///         "\t\tint\n",
+    ///         "\t\tdefault 4\n",
+    ///         "endmenu\n",
+    ///         "\n",
+    ///     )
+    /// );
+    /// ```
+    pub fn format_with(&self, options: &FormatOptions) -> String {
+        let formatted = Formatted {
+            kconfig: self,
+            opts: options,
+            mainmenu: true,
+        };
+        formatted.to_string()
+    }
+
+    /// Write the KConfig out as Kconfig text in a canonical form, for a formatter which rewrites
+    /// files in place. Indents are four spaces, properties come in the order `Display` gives
+    /// them, `select`s and `imply`s are sorted by target, and menus are followed by a blank line.
+    /// Current values are left out, as is the `mainmenu` unless the configuration has one.
+    ///
+    /// Parsing the result gives back the same configuration, apart from the order of the
+    /// `select`s and `imply`s, so formatting canonical text again changes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Entry, KConfig, Menu};
+    ///
+    /// // spans point into the text parsed, which formatting changes
+    /// fn unspanned(mut kconfig: KConfig) -> KConfig {
+    ///     fn clear(menu: &mut Menu) {
+    ///         menu.span = None;
+    ///         for ent in &mut menu.entries {
+    ///             if let Entry::Menu(m) = ent {
+    ///                 clear(m);
+    ///             }
+    ///         }
+    ///     }
+    ///     clear(&mut kconfig.root);
+    ///     kconfig.vars.values_mut().for_each(|var| var.span = None);
+    ///     kconfig
+    /// }
+    ///
+    /// let text = r#"
+    /// mainmenu "Example"
+    /// menu "Build"
+    ///   config CC
+    ///     default "gcc"
+    ///       string   "Compiler"
+    ///   config SMP
+    ///     bool
+    ///     select CRC32
+    ///     depends on PCI
+    ///     depends on NET && (USB || !CRC32)
+    ///     help
+    ///       Run on more than one CPU.
+    ///
+    ///       Say Y if unsure.
+    /// endmenu
+    /// config PCI
+    ///   bool
+    /// choice
+    ///   prompt "Network"
+    ///   config NET
+    ///     bool "Networking"
+    ///   config USB
+    ///     bool "USB"
+    /// endchoice
+    /// config CRC32
+    ///   bool
+    /// "#;
+    /// let kconfig = parser::parse_str(text).unwrap();
+    /// let canonical = kconfig.fmt_canonical();
+    /// let again = parser::parse_str(&canonical).unwrap();
+    /// assert_eq!(unspanned(again), unspanned(kconfig));
+    /// assert_eq!(parser::parse_str(&canonical).unwrap().fmt_canonical(), canonical);
+    ///
+    /// // selects are sorted, and text without a `mainmenu` doesn't gain one
+    /// let text = "config A\n    bool\n    select C\n    select B\nconfig B\n    bool\nconfig C\n    bool\n";
+    /// let canonical = parser::parse_str(text).unwrap().fmt_canonical();
+    /// assert_eq!(
+    ///     canonical,
+    ///     "config A\n    bool\n    select B\n    select C\nconfig B\n    bool\nconfig C\n    bool\n"
+    /// );
+    /// ```
+    pub fn fmt_canonical(&self) -> String {
+        let mut canonical = self.clone();
+        for var in canonical.vars.values_mut() {
+            var.value = None;
+            var.selects.sort_by(|(a, _), (b, _)| a.cmp(b));
+            var.implies.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+        let formatted = Formatted {
+            kconfig: &canonical,
+            opts: &FormatOptions::default(),
+            // without a `mainmenu` the root keeps the name `KConfig::new` gives it
+            mainmenu: self.root.name != KConfig::new().root.name,
+        };
+        let text = formatted.to_string();
+        match text.trim_end() {
+            "" => String::new(),
+            text => format!("{text}\n"),
+        }
+    }
+
+    /// Render the KConfig's current value state as a C header, like the kernel's `autoconf.h`.
+    /// Each enabled variable becomes a `#define CONFIG_<NAME>`, with `y` defined as `1` and
+    /// strings quoted. Variables which are `n` or not set are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// for (name, value) in [
+    ///     ("SMP", Value::Bool(true)),
+    ///     ("NUMA", Value::Bool(false)),
+    ///     ("NR_CPUS", Value::Int(64)),
+    ///     ("PAGE_OFFSET", Value::from(0xc0000000u64)),
+    ///     ("CC", Value::String("gcc \"12\"".to_string())),
+    /// ] {
+    ///     let mut var = Variable::new(name);
+    ///     var.value = Some(value);
+    ///     kconfig.add_var(var);
+    /// }
+    ///
+    /// assert_eq!(
+    ///     kconfig.to_c_header(),
+    ///     "#define CONFIG_SMP 1\n\
+    ///      #define CONFIG_NR_CPUS 64\n\
+    ///      #define CONFIG_PAGE_OFFSET 0xc0000000\n\
+    ///      #define CONFIG_CC \"gcc \\\"12\\\"\"\n"
+    /// );
+    /// ```
+    pub fn to_c_header(&self) -> String {
+        let mut out = String::new();
+        for (k, v) in self.enabled() {
+            let v = match v {
+                Value::Bool(_) => "1".to_string(),
+                Value::String(s) => quote(s),
+                v => v.to_string(),
+            };
+            out.push_str(&format!("#define CONFIG_{k} {v}\n"));
+        }
+        out
+    }
+
+    /// Render the KConfig's enabled bools as `cargo:rustc-cfg` directives, one per line, so that
+    /// a build script can gate code on them with `#[cfg(CONFIG_<NAME>)]`. Int, hex and string
+    /// values can't be cfg flags; use [`to_rust_consts`](KConfig::to_rust_consts) for those.
+    ///
+    /// A `build.rs` would typically look like:
+    ///
+    /// ```no_run
+    /// use konf::parser;
+    ///
+    /// fn main() {
+    ///     let mut kconfig = parser::parse_file("Kconfig").unwrap();
+    ///     kconfig.load_default();
+    ///     kconfig.load(".config").unwrap();
+    ///
+    ///     print!("{}", kconfig.to_rust_cfg());
+    ///     let out = std::env::var("OUT_DIR").unwrap();
+    ///     std::fs::write(format!("{out}/config.rs"), kconfig.to_rust_consts()).unwrap();
+    ///     println!("cargo:rerun-if-changed=Kconfig");
+    ///     println!("cargo:rerun-if-changed=.config");
+    /// }
+    /// ```
+    ///
+    /// with the constants pulled in by `include!(concat!(env!("OUT_DIR"), "/config.rs"));`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// for (name, value) in [
+    ///     ("SMP", Value::Bool(true)),
+    ///     ("NUMA", Value::Bool(false)),
+    ///     ("NR_CPUS", Value::Int(64)),
+    ///     ("PAGE_OFFSET", Value::from(0xc0000000u64)),
+    ///     ("CC", Value::String("gcc".to_string())),
+    /// ] {
+    ///     let mut var = Variable::new(name);
+    ///     var.value = Some(value);
+    ///     kconfig.add_var(var);
+    /// }
+    ///
+    /// assert_eq!(kconfig.to_rust_cfg(), "cargo:rustc-cfg=CONFIG_SMP\n");
+    /// assert_eq!(
+    ///     kconfig.to_rust_consts(),
+    ///     "pub const CONFIG_NR_CPUS: i64 = 64;\n\
+    ///      pub const CONFIG_PAGE_OFFSET: u64 = 0xc0000000;\n\
+    ///      pub const CONFIG_CC: &str = \"gcc\";\n"
+    /// );
+    /// ```
+    pub fn to_rust_cfg(&self) -> String {
+        let mut out = String::new();
+        for (k, v) in self.enabled() {
+            if let Value::Bool(_) = v {
+                out.push_str(&format!("cargo:rustc-cfg=CONFIG_{k}\n"));
+            }
+        }
+        out
+    }
+
+    /// Render the KConfig's enabled int, hex and string values as Rust `const` items, to be
+    /// `include!`d into a crate. See [`to_rust_cfg`](KConfig::to_rust_cfg) for the bools.
+    pub fn to_rust_consts(&self) -> String {
+        let mut out = String::new();
+        for (k, v) in self.enabled() {
+            let (ty, v) = match v {
+                Value::Bool(_) => continue,
+                Value::Int(i) => ("i64", i.to_string()),
+                Value::Hex(h) => ("u64", h.to_string()),
+                Value::String(s) => ("&str", format!("{s:?}")),
+            };
+            out.push_str(&format!("pub const CONFIG_{k}: {ty} = {v};\n"));
+        }
+        out
+    }
+
+    /// Render the relationships between symbols as a Graphviz DOT graph. Each symbol is a node,
+    /// with a solid edge to each symbol its `depends on` refers to, and a dashed edge to each
+    /// symbol it selects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config NET
+    ///         bool
+    ///     config CRC32
+    ///         bool
+    ///     config WLAN
+    ///         bool
+    ///         depends on NET
+    ///         select CRC32
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     kconfig.to_dot(),
+    ///     concat!(
+    ///         "digraph \"config\" {\n",
+    ///         "    \"NET\";\n",
+    ///         "    \"CRC32\";\n",
+    ///         "    \"WLAN\";\n",
+    ///         "    \"WLAN\" -> \"NET\";\n",
+    ///         "    \"WLAN\" -> \"CRC32\" [style=dashed];\n",
+    ///         "}\n",
+    ///     )
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut out = format!("digraph {} {{\n", quote(&self.name));
+        for name in self.vars.keys() {
+            out.push_str(&format!("    {};\n", quote(name)));
+        }
+        for var in self.vars.values() {
+            let from = quote(&var.name);
+            let depends = var.depends.as_ref().map(Expr::symbols).unwrap_or_default();
+            let mut seen = HashSet::new();
+            for dep in depends.into_iter().filter(|dep| seen.insert(*dep)) {
+                out.push_str(&format!("    {from} -> {};\n", quote(dep)));
+            }
+            for (target, _) in &var.selects {
+                out.push_str(&format!(
+                    "    {from} -> {} [style=dashed];\n",
+                    quote(target)
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render the KConfig's current value state as a Makefile fragment, like the kernel's
+    /// `auto.conf`. This is the `.config` text without the `is not set` lines: only enabled
+    /// variables are written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// for (name, value) in [
+    ///     ("SMP", Some(Value::Bool(true))),
+    ///     ("NUMA", Some(Value::Bool(false))),
+    ///     ("DEBUG", None),
+    ///     ("NR_CPUS", Some(Value::Int(64))),
+    /// ] {
+    ///     let mut var = Variable::new(name);
+    ///     var.value = value;
+    ///     kconfig.add_var(var);
+    /// }
+    ///
+    /// let make = kconfig.to_make_fragment();
+    /// assert_eq!(make, "CONFIG_SMP=y\nCONFIG_NR_CPUS=64\n");
+    /// assert!(!make.contains("NUMA"));
+    /// assert!(!make.contains("DEBUG"));
+    /// ```
+    pub fn to_make_fragment(&self) -> String {
+        let mut out = String::new();
+        for (k, v) in self.enabled() {
+            out.push_str(&format!("CONFIG_{k}={}\n", config_value(v)));
+        }
+        out
+    }
+
+    /// Clear every value, leaving all variables unset. Unlike
+    /// [`load_default`](KConfig::load_default), defaults aren't filled in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    /// use std::io::Cursor;
+    ///
+    /// let mut kconfig = parser::parse_str("config GCC\n bool\n default y\nconfig JOBS\n int\n").unwrap();
+    /// kconfig.load_from(Cursor::new("CONFIG_JOBS=8\n")).unwrap();
+    /// kconfig.reset();
+    /// assert!(kconfig.save().values().all(Option::is_none));
+    /// assert_eq!(kconfig.effective_value("GCC"), Some(Value::Bool(true)));
+    /// ```
+    pub fn reset(&mut self) {
+        for var in self.vars.values_mut() {
+            var.value = None;
+        }
+    }
+
+    /// Load the default configuration from the `default` values. Choices with a `default`
+    /// member and nothing else chosen get that member.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// let mut var = Variable::new("DEBUG");
+    /// var.defaults.push((Value::Bool(true).into(), None));
+    /// kconfig.add_var(var);
+    /// kconfig.load_default();
+    /// assert_eq!(kconfig.vars["DEBUG"].value, Some(Value::Bool(true)));
+    /// ```
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let src = "
+    /// choice
+    ///     prompt \"Compiler\"
+    ///     default CLANG
+    /// config GCC
+    ///     bool
+    /// config CLANG
+    ///     bool
+    /// endchoice
+    /// ";
+    /// let mut kconfig = parser::parse_str(src).unwrap();
+    /// kconfig.load_default();
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(false)));
+    /// assert_eq!(kconfig.vars["CLANG"].value, Some(Value::Bool(true)));
+    /// assert!(kconfig.validate().is_ok());
+    /// ```
+    pub fn load_default(&mut self) {
+        self.reset();
+        self.resolve_defaults();
+        self.select_choice_defaults();
+    }
+
+    /// Choose the `default` member of every choice which has nothing chosen yet
+    fn select_choice_defaults(&mut self) {
+        let defaults: Vec<String> = self
+            .choices()
+            .into_iter()
+            .filter(|c| {
+                c.members
+                    .iter()
+                    .all(|m| self.effective_value(m) != Some(Value::Bool(true)))
+            })
+            .filter_map(|c| c.default.clone().filter(|d| c.members.contains(d)))
+            .collect();
+        for default in defaults {
+            // the default was checked to be a member, so this can't fail
+            let _ = self.select_choice(&default);
+        }
+    }
+
+    /// Randomly assign a value to every variable, seeded by `seed` so the same seed always
+    /// produces the same configuration. Bools are flipped at random, and ints and hex values are
+    /// picked from within their `range`, or from every value of their type if they have none.
+    /// Everything else falls back to its `default`. Each
+    /// choice gets exactly one member chosen at random, or at most one if it is `optional`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Type, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// for name in ["GCC", "CLANG", "DEBUG", "LTO"] {
+    ///     let mut var = Variable::new(name);
+    ///     var.ty = Some(Type::Bool);
+    ///     kconfig.add_var(var);
+    /// }
+    /// let mut jobs = Variable::new("JOBS");
+    /// jobs.ty = Some(Type::Int);
+    /// jobs.range = Some((Value::Int(1).into(), Value::Int(16).into()));
+    /// kconfig.add_var(jobs);
+    ///
+    /// kconfig.randconfig(1);
+    /// let first = kconfig.save();
+    /// kconfig.randconfig(1);
+    /// assert_eq!(kconfig.save(), first);
+    ///
+    /// kconfig.randconfig(2);
+    /// assert_ne!(kconfig.save(), first);
+    ///
+    /// for seed in 0..100 {
+    ///     kconfig.randconfig(seed);
+    ///     let jobs = kconfig.vars["JOBS"].value.as_ref().unwrap();
+    ///     assert!((1..=16).contains(&jobs.as_int().unwrap()));
+    /// }
+    ///
+    /// // ints without a range can be anything
+    /// let mut seed_var = Variable::new("SEED");
+    /// seed_var.ty = Some(Type::Int);
+    /// kconfig.add_var(seed_var);
+    /// let seeds: std::collections::HashSet<_> = (0..20)
+    ///     .map(|seed| {
+    ///         kconfig.randconfig(seed);
+    ///         kconfig.vars["SEED"].value.as_ref().unwrap().as_int().unwrap()
+    ///     })
+    ///     .collect();
+    /// assert_eq!(seeds.len(), 20);
+    /// ```
+    ///
+    /// The result always satisfies any choices:
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     choice
+    ///         prompt "Compiler"
+    ///     config GCC
+    ///         bool
+    ///     config CLANG
+    ///         bool
+    ///     endchoice
+    ///     choice
+    ///         prompt "Linker"
+    ///         optional
+    ///     config LLD
+    ///         bool
+    ///     config MOLD
+    ///         bool
+    ///     endchoice
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// for seed in 0..50 {
+    ///     kconfig.randconfig(seed);
+    ///     assert!(kconfig.validate().is_ok(), "seed {seed}");
+    /// }
+    /// ```
+    pub fn randconfig(&mut self, seed: u64) {
+        let mut rng = Rng(seed);
+        let defaults: Vec<_> = self.vars.keys().map(|k| self.default_value(k)).collect();
+        let ranges: Vec<_> = self
+            .vars
+            .values()
+            .map(|v| self.range_of(v).ok().flatten())
+            .collect();
+        for ((var, default), range) in self.vars.values_mut().zip(defaults).zip(ranges) {
+            var.value = match (var.ty, &range) {
+                (Some(Type::Bool), _) => Some(Value::Bool(rng.next() & 1 == 1)),
+                (Some(Type::Int), Some((Value::Int(min), Value::Int(max)))) => {
+                    Some(Value::Int(rng.range(*min as i128, *max as i128) as i64))
+                }
+                (Some(Type::Hex), Some((Value::Hex(min), Value::Hex(max)))) => {
+                    let h = rng.range(min.value as i128, max.value as i128) as u64;
+                    Some(Value::from(h))
+                }
+                // without a range, any value of the type will do
+                (Some(Type::Int), None) => Some(Value::Int(rng.next() as i64)),
+                (Some(Type::Hex), None) => Some(Value::from(rng.next())),
+                _ => default,
+            };
+        }
+
+        let choices: Vec<_> = self
+            .choices()
+            .into_iter()
+            .map(|c| (c.members.clone(), c.optional))
+            .collect();
+        for (members, optional) in choices {
+            if members.is_empty() {
+                continue;
+            }
+            // an optional choice may also have nothing chosen, picked as one past the end
+            let last = members.len() as i128 - i128::from(!optional);
+            let chosen = rng.range(0, last) as usize;
+            for (i, m) in members.iter().enumerate() {
+                if let Some(var) = self.vars.get_mut(m) {
+                    var.value = Some(Value::Bool(i == chosen));
+                }
+            }
+        }
+    }
+
+    /// Whether any variable has been set to something other than its default. Unset variables
+    /// never count as modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config GCC\n bool\n default y\nconfig JOBS\n int\n default 4\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.load_default();
+    /// assert!(!kconfig.is_modified());
+    ///
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(8));
+    /// assert!(kconfig.is_modified());
+    /// assert_eq!(kconfig.modified_symbols(), ["JOBS"]);
+    /// ```
+    pub fn is_modified(&self) -> bool {
+        !self.modified_symbols().is_empty()
+    }
+
+    /// The names of the variables set to something other than their default, in declaration
+    /// order
+    pub fn modified_symbols(&self) -> Vec<&str> {
+        self.vars
+            .values()
+            .filter(|var| var.value.is_some() && var.value != self.default_value(&var.name))
+            .map(|var| var.name.as_str())
+            .collect()
+    }
+
+    /// List every variable whose effective value differs between `self` and `other`, as
+    /// `(name, old, new)`. Variables that only exist on one side are reported with `None` on
+    /// the other. Use [`format_diff`] to render the result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let mut old = KConfig::new();
+    /// let mut debug = Variable::new("DEBUG");
+    /// debug.value = Some(Value::Bool(true));
+    /// old.add_var(debug);
+    /// let mut jobs = Variable::new("JOBS");
+    /// jobs.value = Some(Value::Int(4));
+    /// old.add_var(jobs);
+    ///
+    /// let mut new = KConfig::new();
+    /// let mut debug = Variable::new("DEBUG");
+    /// debug.value = Some(Value::Bool(false));
+    /// new.add_var(debug);
+    /// let mut jobs = Variable::new("JOBS");
+    /// jobs.value = Some(Value::Int(8));
+    /// new.add_var(jobs);
+    /// let mut lto = Variable::new("LTO");
+    /// lto.value = Some(Value::Bool(true));
+    /// new.add_var(lto);
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(
+    ///     diff,
+    ///     vec![
+    ///         ("DEBUG".to_string(), Some(Value::Bool(true)), Some(Value::Bool(false))),
+    ///         ("JOBS".to_string(), Some(Value::Int(4)), Some(Value::Int(8))),
+    ///         ("LTO".to_string(), None, Some(Value::Bool(true))),
+    ///     ]
+    /// );
+    /// assert_eq!(konf::format_diff(&diff), "DEBUG y -> n\nJOBS 4 -> 8\n+LTO y\n");
+    /// ```
+    pub fn diff(&self, other: &KConfig) -> Vec<(String, Option<Value>, Option<Value>)> {
+        let mut changes = vec![];
+        for name in self.vars.keys() {
+            let old = self.effective_value(name);
+            let new = other.effective_value(name);
+            if old != new {
+                changes.push((name.clone(), old, new));
+            }
+        }
+        for name in other.vars.keys() {
+            if !self.vars.contains_key(name) {
+                if let Some(new) = other.effective_value(name) {
+                    changes.push((name.clone(), None, Some(new)));
+                }
+            }
+        }
+        changes
+    }
+
+    /// The value the variable `name` currently takes: its `value` if one has been set,
+    /// otherwise its default as given by [`default_value`](KConfig::default_value)
+    pub fn effective_value(&self, name: &str) -> Option<Value> {
+        self.resolve(name, self.vars.len())
+    }
+
+    /// The type of the variable `name`: its declared type if it has one, otherwise bool if it is
+    /// the target of a `select` or `imply`, and otherwise the type of its first default which
+    /// has one. `None` if none of those tell, or there is no such variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Type};
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config JOBS
+    ///         int "Parallel jobs"
+    ///     config SMP
+    ///         def_bool y
+    ///         select CRC32
+    ///     config CRC32
+    ///     config NR_CPUS
+    ///         default 64
+    ///     config MAX_CPUS
+    ///         default NR_CPUS
+    ///     config UNKNOWN
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.effective_type("JOBS"), Some(Type::Int));
+    /// assert_eq!(kconfig.effective_type("SMP"), Some(Type::Bool));
+    /// assert_eq!(kconfig.effective_type("CRC32"), Some(Type::Bool));
+    /// assert_eq!(kconfig.effective_type("NR_CPUS"), Some(Type::Int));
+    /// assert_eq!(kconfig.effective_type("MAX_CPUS"), Some(Type::Int));
+    /// assert_eq!(kconfig.effective_type("UNKNOWN"), None);
+    /// assert_eq!(kconfig.effective_type("MISSING"), None);
+    /// ```
+    pub fn effective_type(&self, name: &str) -> Option<Type> {
+        self.type_at(name, self.vars.len())
+    }
+
+    // `depth` bounds how many `default OTHER` are followed, as for `resolve`
+    fn type_at(&self, name: &str, depth: usize) -> Option<Type> {
+        let var = self.vars.get(name)?;
+        if var.ty.is_some() {
+            return var.ty;
+        }
+        let targeted = self
+            .vars
+            .values()
+            .any(|v| (v.selects.iter().chain(&v.implies)).any(|(target, _)| target == name));
+        if targeted {
+            return Some(Type::Bool);
+        }
+        let depth = depth.checked_sub(1)?;
+        var.defaults.iter().find_map(|(default, _)| match default {
+            DefaultKind::Literal(v) => Some(v.type_of()),
+            DefaultKind::Symbol(s) => self.type_at(s, depth),
+        })
+    }
+
+    /// The default of the variable `name`: the first of its defaults whose `if` condition
+    /// holds. A `default OTHER` is resolved to the effective value of `OTHER`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n int\n default B\n config B\n int\n default 3\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.default_value("A"), Some(Value::Int(3)));
+    /// kconfig.vars["B"].value = Some(Value::Int(7));
+    /// assert_eq!(kconfig.default_value("A"), Some(Value::Int(7)));
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config FOO
+    ///         bool
+    ///     config BAR
+    ///         bool
+    ///     config MODE
+    ///         int
+    ///         default 1 if FOO
+    ///         default 2 if BAR && !FOO
+    ///         default 3
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.vars["FOO"].value = Some(Value::Bool(true));
+    /// assert_eq!(kconfig.default_value("MODE"), Some(Value::Int(1)));
+    /// kconfig.vars["FOO"].value = Some(Value::Bool(false));
+    /// kconfig.vars["BAR"].value = Some(Value::Bool(true));
+    /// assert_eq!(kconfig.default_value("MODE"), Some(Value::Int(2)));
+    /// kconfig.vars["BAR"].value = Some(Value::Bool(false));
+    /// assert_eq!(kconfig.default_value("MODE"), Some(Value::Int(3)));
+    /// ```
+    pub fn default_value(&self, name: &str) -> Option<Value> {
+        let var = self.vars.get(name)?;
+        self.resolve_default(var, self.vars.len())
+    }
+
+    // `depth` bounds how many symbol references (through `default OTHER` or conditions) are
+    // followed, so that a cycle of them can't recurse forever
+    pub(crate) fn resolve(&self, name: &str, depth: usize) -> Option<Value> {
+        let var = self.vars.get(name)?;
+        var.value
+            .clone()
+            .or_else(|| self.resolve_default(var, depth))
+    }
+
+    fn resolve_default(&self, var: &Variable, depth: usize) -> Option<Value> {
+        let depth = depth.checked_sub(1)?;
+        let (default, _) = var.defaults.iter().find(|(_, cond)| match cond {
+            Some(cond) => cond.eval_at(self, depth),
+            None => true,
+        })?;
+        match default {
+            DefaultKind::Literal(v) => Some(v.clone()),
+            DefaultKind::Symbol(s) => self.resolve(s, depth),
+        }
+    }
+
+    /// Whether the variable `name` is visible, which is when its `depends on` expression holds
+    /// given the current state. Variables without dependencies are always visible, and unknown
+    /// ones never are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n bool\n config B\n bool\n depends on A\n",
+    /// )
+    /// .unwrap();
+    /// assert!(kconfig.visible("A"));
+    /// kconfig.vars["A"].value = Some(Value::Bool(true));
+    /// assert!(kconfig.visible("B"));
+    /// kconfig.vars["A"].value = Some(Value::Bool(false));
+    /// assert!(!kconfig.visible("B"));
+    /// ```
+    pub fn visible(&self, name: &str) -> bool {
+        match self.vars.get(name) {
+            Some(var) => var.depends.as_ref().is_none_or(|e| e.eval(self)),
+            None => false,
+        }
+    }
+
+    /// Remove the variables which can never be visible, because their `depends on` expression
+    /// is false whatever the configuration, returning their names. A symbol which isn't defined
+    /// is always `n`, and so is one which has been removed, so removing one variable can make
+    /// others removable too. Variables which something `select`s are kept, as a `select` can
+    /// turn them on anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config ARCH_FOO
+    ///         bool
+    ///     config FOO_DRIVER
+    ///         bool
+    ///         depends on ARCH_BAR
+    ///     config FOO_DRIVER_DEBUG
+    ///         bool
+    ///         depends on FOO_DRIVER && ARCH_FOO
+    ///     config PORTABLE
+    ///         bool
+    ///         depends on ARCH_FOO || ARCH_BAR
+    ///     config NEVER
+    ///         bool
+    ///         depends on n
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.prune_invisible(), ["FOO_DRIVER", "NEVER", "FOO_DRIVER_DEBUG"]);
+    /// assert_eq!(kconfig.symbols().collect::<Vec<_>>(), ["ARCH_FOO", "PORTABLE"]);
+    /// assert!(kconfig.prune_invisible().is_empty());
+    /// ```
+    pub fn prune_invisible(&mut self) -> Vec<String> {
+        let mut pruned = vec![];
+        loop {
+            let never: Vec<String> = self
+                .vars
+                .values()
+                .filter(|var| {
+                    let depends = var.depends.as_ref();
+                    depends.is_some_and(|e| e.constant(self) == Some(false))
+                        && !self.is_selected(&var.name)
+                })
+                .map(|var| var.name.clone())
+                .collect();
+            if never.is_empty() {
+                return pruned;
+            }
+            for name in never {
+                self.remove_var(&name);
+                pruned.push(name);
+            }
+        }
+    }
+
+    /// Find every reference to a symbol which isn't defined: in `depends on` expressions,
+    /// `default` symbols, `range` ends, `select`s and `imply`s, and their conditions. Each
+    /// dangling reference is reported once per variable making it, in declaration order.
+    ///
+    /// A `select` or `imply` of a symbol with a type other than bool is reported as well, since
+    /// only a bool can be turned on by one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, ErrorKind};
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config WIFI
+    ///         bool
+    ///         select MISSING
+    ///     config BT
+    ///         bool
+    ///         depends on WIFI
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// let errors = kconfig.check_references();
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].kind(), ErrorKind::UnknownSymbol);
+    /// assert_eq!(errors[0].to_string(), "WIFI refers to undefined symbol MISSING");
+    ///
+    /// let kconfig = parser::parse_str("config SMP\n bool\n select NR_CPUS\nconfig NR_CPUS\n int\n").unwrap();
+    /// let errors = kconfig.check_references();
+    /// assert_eq!(errors[0].kind(), ErrorKind::TypeMismatch);
+    /// assert_eq!(errors[0].to_string(), "SMP selects NR_CPUS, which is int rather than bool");
+    /// ```
+    pub fn check_references(&self) -> Vec<Error> {
+        let mut errors = vec![];
+        for var in self.vars.values() {
+            for (kw, targets) in [("selects", &var.selects), ("implies", &var.implies)] {
+                for (target, _) in targets {
+                    let ty = self.vars.get(target).and_then(|t| t.ty);
+                    if let Some(ty) = ty.filter(|ty| !ty.is_bool()) {
+                        let name = &var.name;
+                        let msg = format!("{name} {kw} {target}, which is {ty} rather than bool");
+                        errors.push(Error::new(ErrorKind::TypeMismatch, msg));
+                    }
+                }
+            }
+            let mut names = var.references();
+            for (target, cond) in var.selects.iter().chain(&var.implies) {
+                names.push(target);
+                if let Some(cond) = cond {
+                    names.extend(cond.symbols());
+                }
+            }
+            let mut seen = HashSet::new();
+            for name in names {
+                if !self.vars.contains_key(name) && seen.insert(name) {
+                    let msg = format!("{} refers to undefined symbol {name}", var.name);
+                    errors.push(Error::new(ErrorKind::UnknownSymbol, msg));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Whether any variable has a `select` of `name`
+    fn is_selected(&self, name: &str) -> bool {
+        self.vars
+            .values()
+            .any(|var| var.selects.iter().any(|(target, _)| target == name))
+    }
+
+    /// The names of the variables whose `depends on` expression refers to `name`, anywhere
+    /// within it. These are the variables which may be hidden when `name` is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config NET
+    ///         bool
+    ///     config PCI
+    ///         bool
+    ///     config WLAN
+    ///         bool
+    ///         depends on NET
+    ///     config E1000
+    ///         bool
+    ///         depends on PCI && (NET || !WLAN)
+    ///     config USB
+    ///         bool
+    ///         depends on PCI
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.symbols_depending_on("NET"), ["WLAN", "E1000"]);
+    /// assert_eq!(kconfig.symbols_depending_on("PCI"), ["E1000", "USB"]);
+    /// assert_eq!(kconfig.symbols_depending_on("WLAN"), ["E1000"]);
+    /// assert!(kconfig.symbols_depending_on("USB").is_empty());
+    /// ```
+    pub fn symbols_depending_on(&self, name: &str) -> Vec<&str> {
+        self.vars
+            .values()
+            .filter(|var| {
+                var.depends
+                    .as_ref()
+                    .is_some_and(|e| e.symbols().contains(&name))
+            })
+            .map(|var| var.name.as_str())
+            .collect()
+    }
+
+    /// Order the variables so that each comes after every variable it refers to, through its
+    /// `depends on` expression or its defaults. Evaluating them in this order means that
+    /// everything a variable needs is already known. Variables which don't depend on each other
+    /// keep the order they were defined in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// // a chain
+    /// let kconfig = parser::parse_str(
+    ///     "config C\n bool\n depends on B\nconfig B\n bool\n default A\nconfig A\n bool\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.dependency_order().unwrap(), ["A", "B", "C"]);
+    ///
+    /// // a diamond
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config TOP
+    ///         bool
+    ///         depends on LEFT && RIGHT
+    ///     config LEFT
+    ///         bool
+    ///         depends on BASE
+    ///     config RIGHT
+    ///         bool
+    ///         default y if BASE
+    ///     config BASE
+    ///         bool
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.dependency_order().unwrap(), ["BASE", "LEFT", "RIGHT", "TOP"]);
+    ///
+    /// // a cycle
+    /// let kconfig = parser::parse_str(
+    ///     "config A\n bool\n depends on B\nconfig B\n bool\n default C\nconfig C\n bool\n depends on A\n",
+    /// )
+    /// .unwrap();
+    /// let err = kconfig.dependency_order().unwrap_err();
+    /// assert_eq!(err.to_string(), "dependency cycle: A -> B -> C -> A");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error naming the variables in a cycle, if there is one.
+    pub fn dependency_order(&self) -> Result<Vec<String>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Visiting,
+            Done,
+        }
+
+        fn visit<'a>(
+            kconfig: &'a KConfig,
+            name: &'a str,
+            marks: &mut IndexMap<&'a str, Mark>,
+            stack: &mut Vec<&'a str>,
+            out: &mut Vec<String>,
+        ) -> Result<()> {
+            match marks.get(name) {
+                Some(Mark::New) => {}
+                Some(Mark::Visiting) => {
+                    let start = stack.iter().position(|s| *s == name).unwrap();
+                    let mut cycle = stack[start..].to_vec();
+                    cycle.push(name);
+                    let msg = format!("dependency cycle: {}", cycle.join(" -> "));
+                    return Err(Error::new(ErrorKind::Other, msg));
+                }
+                // done already, or not a variable we know about
+                _ => return Ok(()),
+            }
+            marks.insert(name, Mark::Visiting);
+            stack.push(name);
+            for dep in kconfig.vars[name].references() {
+                visit(kconfig, dep, marks, stack, out)?;
+            }
+            stack.pop();
+            marks.insert(name, Mark::Done);
+            out.push(name.to_string());
+            Ok(())
+        }
+
+        let mut marks: IndexMap<&str, Mark> =
+            self.vars.keys().map(|k| (k.as_str(), Mark::New)).collect();
+        let mut stack = vec![];
+        let mut out = vec![];
+        for name in self.vars.keys() {
+            visit(self, name, &mut marks, &mut stack, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Revert every variable which isn't [`visible`](KConfig::visible) to its default, as
+    /// [`evaluate`](KConfig::evaluate) does. This stops values which were set before a
+    /// dependency was disabled from leaking into a saved config.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    /// use std::io::Cursor;
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n bool\n config B\n bool\n depends on A\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.load_from(Cursor::new("CONFIG_A=y\nCONFIG_B=y\n")).unwrap();
+    /// kconfig.vars["A"].value = Some(Value::Bool(false));
+    ///
+    /// assert_eq!(
+    ///     kconfig.save_config_string(),
+    ///     "# CONFIG_A is not set\n# CONFIG_B is not set\n"
+    /// );
+    /// kconfig.normalize();
+    /// assert_eq!(kconfig.vars["B"].value, None);
+    /// ```
+    ///
+    /// Hidden variables with a default take it, both here and when saved:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n bool\nconfig JOBS\n int\n default 4\n depends on A\nconfig SMP\n bool\n default y\n depends on A\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.vars["JOBS"].value = Some(Value::Int(16));
+    /// kconfig.vars["SMP"].value = Some(Value::Bool(false));
+    ///
+    /// assert_eq!(
+    ///     kconfig.save_config_string(),
+    ///     "# CONFIG_A is not set\nCONFIG_JOBS=4\nCONFIG_SMP=y\n"
+    /// );
+    /// let mut evaluated = kconfig.clone();
+    /// evaluated.evaluate().unwrap();
+    /// kconfig.normalize();
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(4)));
+    /// assert_eq!(kconfig.vars["SMP"].value, Some(Value::Bool(true)));
+    /// assert_eq!(evaluated.vars["JOBS"].value, kconfig.vars["JOBS"].value);
+    /// assert_eq!(evaluated.vars["SMP"].value, kconfig.vars["SMP"].value);
+    /// ```
+    pub fn normalize(&mut self) {
+        let hidden: Vec<_> = self
+            .vars
+            .keys()
+            .filter(|k| !self.visible(k))
+            .map(|k| (k.clone(), self.default_value(k)))
+            .collect();
+        for (name, default) in hidden {
+            self.vars[&name].value = default;
+        }
+    }
+
+    /// Find the menu called `name` anywhere in the menu tree. See [`Menu::find`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Drivers"
+    ///         menu "Network"
+    ///             menu "Wireless"
+    ///                 config WIFI
+    ///             endmenu
+    ///         endmenu
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(kconfig.find_menu("Wireless").unwrap().entries.len(), 1);
+    /// assert!(kconfig.find_menu("Sound").is_none());
+    /// ```
+    pub fn find_menu(&self, name: &str) -> Option<&Menu> {
+        self.root.find(name)
+    }
+
+    /// Rename the menu `old` to `new`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let mut kconfig = parser::parse_str("menu \"Net\"\nendmenu\nmenu \"Sound\"\nendmenu\n").unwrap();
+    /// kconfig.rename_menu("Net", "Networking").unwrap();
+    /// assert!(kconfig.find_menu("Networking").is_some());
+    /// assert!(kconfig.find_menu("Net").is_none());
+    ///
+    /// assert!(kconfig.rename_menu("Net", "Network").is_err());
+    /// assert!(kconfig.rename_menu("Networking", "Sound").is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there is no menu called `old`, or there is already
+    /// one called `new`.
+    pub fn rename_menu(&mut self, old: &str, new: &str) -> Result<()> {
+        if self.root.find(new).is_some() {
+            let msg = format!("menu \"{new}\" already exists");
+            return Err(Error::new(ErrorKind::Other, msg));
+        }
+        let menu = self.root.find_mut(old).ok_or_else(|| no_menu(old))?;
+        menu.name = new.to_string();
+        Ok(())
+    }
+
+    /// Move the entry at index `idx` of the menu `from_menu` to index `to_idx` of the menu
+    /// `to_menu`, as a drag and drop in an editor would. `to_idx` is a position in `to_menu`
+    /// once the entry has been taken out, so moving within one menu works as expected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Entry};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Build"
+    ///         config GCC
+    ///         config CLANG
+    ///     endmenu
+    ///     menu "Debug"
+    ///         config KASAN
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.move_entry("Build", 1, "Debug", 0).unwrap();
+    /// let var = |name: &str| Entry::Variable(name.to_string());
+    /// assert_eq!(kconfig.find_menu("Build").unwrap().entries, [var("GCC")]);
+    /// assert_eq!(kconfig.find_menu("Debug").unwrap().entries, [var("CLANG"), var("KASAN")]);
+    ///
+    /// assert!(kconfig.move_entry("Build", 1, "Debug", 0).is_err());
+    /// assert!(kconfig.move_entry("Build", 0, "Debug", 3).is_err());
+    /// assert!(kconfig.move_entry("Build", 0, "Sound", 0).is_err());
+    ///
+    /// // a menu can't be moved inside itself
+    /// let top = kconfig.root.name.clone();
+    /// assert!(kconfig.move_entry(&top, 0, "Build", 0).is_err());
+    /// assert_eq!(kconfig.find_menu("Build").unwrap().entries.len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if either menu doesn't exist, if either index is out
+    /// of range, or if a menu would be moved inside itself. The tree is then left unchanged.
+    pub fn move_entry(
+        &mut self,
+        from_menu: &str,
+        idx: usize,
+        to_menu: &str,
+        to_idx: usize,
+    ) -> Result<()> {
+        if self.root.find(to_menu).is_none() {
+            return Err(no_menu(to_menu));
+        }
+        let from = self
+            .root
+            .find_mut(from_menu)
+            .ok_or_else(|| no_menu(from_menu))?;
+        if idx >= from.entries.len() {
+            let msg = format!("menu \"{from_menu}\" has no entry {idx}");
+            return Err(Error::new(ErrorKind::Other, msg));
+        }
+        let entry = from.entries.remove(idx);
+        let err = match self.root.find_mut(to_menu) {
+            Some(to) if to_idx <= to.entries.len() => {
+                to.entries.insert(to_idx, entry);
+                return Ok(());
+            }
+            Some(_) => format!("menu \"{to_menu}\" has no position {to_idx}"),
+            // the target went out along with the entry
+            None => format!("menu \"{to_menu}\" is inside the entry being moved"),
+        };
+        if let Some(from) = self.root.find_mut(from_menu) {
+            from.entries.insert(idx, entry);
+        }
+        Err(Error::new(ErrorKind::Other, err))
+    }
+
+    /// Iterate over the variables bound into the menu tree in declaration order, walking `root`
+    /// depth-first. Each variable comes with the chain of menus enclosing it, starting at
+    /// `root`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config A
+    ///     menu "Outer"
+    ///         config B
+    ///         menu "Inner"
+    ///             config C
+    ///         endmenu
+    ///         config D
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let tree: Vec<_> = kconfig
+    ///     .iter_tree()
+    ///     .map(|(path, var)| {
+    ///         let path: Vec<_> = path.iter().map(|m| m.name.as_str()).collect();
+    ///         (path, var.name.as_str())
+    ///     })
+    ///     .collect();
+    /// assert_eq!(
+    ///     tree,
+    ///     vec![
+    ///         (vec!["(top)"], "A"),
+    ///         (vec!["(top)", "Outer"], "B"),
+    ///         (vec!["(top)", "Outer", "Inner"], "C"),
+    ///         (vec!["(top)", "Outer"], "D"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter_tree(&self) -> impl Iterator<Item = (Vec<&Menu>, &Variable)> {
+        TreeIter::new(self)
+    }
+
+    /// Render the menu tree as an indented outline of menus and the symbols in them, with
+    /// their current values, like a `menuconfig` screen. Bools are shown as `[*]` or `[ ]`,
+    /// and other values in parentheses. Unlike `Display`, this isn't Kconfig syntax.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::parser;
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config MODULES
+    ///         bool
+    ///         default y
+    ///     menu "Networking"
+    ///         config TCP
+    ///             bool
+    ///             default y
+    ///         config UDP
+    ///             bool
+    ///         menu "Tuning"
+    ///             config BUFFER
+    ///                 hex
+    ///                 default 0x1000
+    ///             config HOSTNAME
+    ///                 string
+    ///         endmenu
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.load_default();
+    ///
+    /// assert_eq!(
+    ///     kconfig.menu_tree_string(),
+    ///     "[*] MODULES\n\
+    ///      Networking\n\
+    ///      \x20 [*] TCP\n\
+    ///      \x20 [ ] UDP\n\
+    ///      \x20 Tuning\n\
+    ///      \x20   (0x1000) BUFFER\n\
+    ///      \x20   () HOSTNAME\n"
+    /// );
+    /// ```
+    pub fn menu_tree_string(&self) -> String {
+        let mut out = String::new();
+        self.root.outline(self, 0, &mut out);
+        out
+    }
+
+    /// The names of the menus leading to `symbol`, starting at `root`. Returns `None` if the
+    /// symbol isn't bound into any menu, for example because it was only added with
+    /// [`add_var`](KConfig::add_var).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Variable};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Networking"
+    ///         menu "TCP"
+    ///             config TCP_FASTOPEN
+    ///         endmenu
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     kconfig.path_to("TCP_FASTOPEN"),
+    ///     Some(vec!["(top)".to_string(), "Networking".to_string(), "TCP".to_string()])
+    /// );
+    ///
+    /// kconfig.add_var(Variable::new("UNBOUND"));
+    /// assert_eq!(kconfig.path_to("UNBOUND"), None);
+    /// ```
+    pub fn path_to(&self, symbol: &str) -> Option<Vec<String>> {
+        self.symbol_entry(symbol).map(|(path, _)| path)
+    }
+
+    /// Get the variable `symbol` along with the path of menus leading to it, as
+    /// [`path_to`](KConfig::path_to) gives, in one walk of the tree which stops once it is
+    /// found. Returns `None` if there is no such variable or it isn't in any menu.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Type, Variable};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     menu "Networking"
+    ///         menu "TCP"
+    ///             config TCP_FASTOPEN
+    ///                 bool "TCP Fast Open"
+    ///         endmenu
+    ///     endmenu
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// let (path, var) = kconfig.symbol_entry("TCP_FASTOPEN").unwrap();
+    /// assert_eq!(path, ["(top)", "Networking", "TCP"]);
+    /// assert_eq!(var.ty, Some(Type::Bool));
+    /// assert_eq!(var.desc.as_deref(), Some("TCP Fast Open"));
+    ///
+    /// kconfig.add_var(Variable::new("UNBOUND"));
+    /// assert!(kconfig.symbol_entry("UNBOUND").is_none());
+    /// assert!(kconfig.symbol_entry("MISSING").is_none());
+    /// ```
+    pub fn symbol_entry(&self, symbol: &str) -> Option<(Vec<String>, &Variable)> {
+        let mut iter = TreeIter::new(self);
+        while let Some(var) = iter.next_var() {
+            if var.name == symbol {
+                let path = iter.path().iter().map(|m| m.name.clone()).collect();
+                return Some((path, var));
+            }
+        }
+        None
+    }
+
+    /// Give every variable which doesn't have a value its default. Unlike
+    /// [`load_default`](KConfig::load_default), values which are already set are kept, and a
+    /// `default OTHER` copies in the current effective value of `OTHER`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "config A\n int\n default B\n config B\n int\n default 3\n",
+    /// )
+    /// .unwrap();
+    /// kconfig.vars["B"].value = Some(Value::Int(7));
+    /// kconfig.resolve_defaults();
+    /// assert_eq!(kconfig.vars["A"].value, Some(Value::Int(7)));
+    /// assert_eq!(kconfig.vars["B"].value, Some(Value::Int(7)));
+    /// ```
+    pub fn resolve_defaults(&mut self) {
+        let values: Vec<_> = self.vars.keys().map(|k| self.effective_value(k)).collect();
+        for (var, value) in self.vars.values_mut().zip(values) {
+            var.value = value;
+        }
+    }
+
+    /// Bring every value up to date with the rest of the configuration, as the kernel's config
+    /// tools do after any change. First, choices with nothing chosen get their `default`
+    /// member. Then in each round, selected symbols are forced to `y`, hidden ones are reset to
+    /// their default, unset implied symbols become `y`, and everything else unset gets its
+    /// default. Rounds are repeated until nothing changes, so calling this again on the result
+    /// changes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config WIFI
+    ///         bool
+    ///         select CRYPTO
+    ///         imply LEDS
+    ///     config CRYPTO
+    ///         bool
+    ///         select AES if FAST
+    ///     config AES
+    ///         bool
+    ///     config FAST
+    ///         bool
+    ///         default y
+    ///     config LEDS
+    ///         bool
+    ///     config LED_BLINK
+    ///         bool
+    ///         default y
+    ///         depends on LEDS
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// kconfig.vars["WIFI"].value = Some(Value::Bool(true));
+    /// kconfig.evaluate().unwrap();
+    ///
+    /// for name in ["WIFI", "CRYPTO", "AES", "FAST", "LEDS", "LED_BLINK"] {
+    ///     assert_eq!(kconfig.vars[name].value, Some(Value::Bool(true)), "{name}");
+    /// }
+    ///
+    /// let settled = kconfig.clone();
+    /// kconfig.evaluate().unwrap();
+    /// assert_eq!(kconfig, settled);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the values are still changing after as many rounds
+    /// as there are variables.
+    pub fn evaluate(&mut self) -> Result<()> {
+        self.select_choice_defaults();
+        let rounds = self.vars.len() + 1;
+        for _ in 0..rounds {
+            let values: Vec<_> = self.vars.keys().map(|k| self.evaluated_value(k)).collect();
+            let mut changed = false;
+            for (var, value) in self.vars.values_mut().zip(values) {
+                changed |= var.value != value;
+                var.value = value;
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+        let msg = format!("configuration did not settle after {rounds} rounds");
+        Err(Error::new(ErrorKind::Other, msg))
+    }
+
+    /// The value `name` should have after one round of [`evaluate`](KConfig::evaluate)
+    fn evaluated_value(&self, name: &str) -> Option<Value> {
+        if self.is_forced(name) {
+            return Some(Value::Bool(true));
+        }
+        if !self.visible(name) {
+            return self.default_value(name);
+        }
+        if self.vars[name].value.is_none() && self.triggered(name, |var| &var.implies) {
+            return Some(Value::Bool(true));
+        }
+        self.effective_value(name)
+    }
+
+    /// Whether any visible variable which is `y` names `name` in the list `targets` picks out,
+    /// with its condition holding
+    fn triggered(
+        &self,
+        name: &str,
+        targets: impl Fn(&Variable) -> &Vec<(String, Option<Expr>)>,
+    ) -> bool {
+        self.vars.values().any(|var| {
+            targets(var).iter().any(|(target, cond)| {
+                target == name
+                    && cond.as_ref().is_none_or(|c| c.eval(self))
+                    && self.visible(&var.name)
+                    && self.effective_value(&var.name) == Some(Value::Bool(true))
+            })
+        })
+    }
+
+    /// Load a `.config` file located at `config_file` into the KConfig's state
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    ///
+    /// let path = std::env::temp_dir().join(format!("konf-load-example-{}", std::process::id()));
+    /// std::fs::write(&path, "CONFIG_DEBUG=y\n# CONFIG_CLANG is not set\n").unwrap();
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// kconfig.add_var(Variable::new("DEBUG"));
+    /// kconfig.add_var(Variable::new("CLANG"));
+    /// kconfig.load(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(kconfig.vars["DEBUG"].value, Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.vars["CLANG"].value, Some(Value::Bool(false)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file at `config_file` is not found, or it is
+    /// malformed in any way.
+    pub fn load(&mut self, config_file: &str) -> io::Result<()> {
+        let file = std::fs::File::open(config_file)?;
+        self.load_from(file)
+    }
+
+    /// Load `.config` formatted text from any reader into the KConfig's state
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{KConfig, Value, Variable};
+    /// use std::io::Cursor;
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// for name in ["GCC", "CLANG", "JOBS"] {
+    ///     kconfig.add_var(Variable::new(name));
+    /// }
+    ///
+    /// let config = "CONFIG_GCC=y\n# CONFIG_CLANG is not set\nCONFIG_JOBS=8\n";
+    /// kconfig.load_from(Cursor::new(config)).unwrap();
+    /// assert_eq!(kconfig.vars["GCC"].value, Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.vars["CLANG"].value, Some(Value::Bool(false)));
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(8)));
+    ///
+    /// // values which don't fit in 64 bits are an error
+    /// let config = "CONFIG_GCC=n\nCONFIG_JOBS=9223372036854775808\n";
+    /// let err = kconfig.load_from(Cursor::new(config)).unwrap_err();
+    /// assert_eq!(err.to_string(), "line 2: int `9223372036854775808` is out of range");
+    ///
+    /// // there are no tristate symbols, so `m` is never a valid value
+    /// let err = kconfig.load_from(Cursor::new("CONFIG_GCC=m\n")).unwrap_err();
+    /// assert_eq!(err.to_string(), "line 1: `m` is not a valid value for GCC");
+    /// ```
+    ///
+    /// Values must suit the type of their symbol, though a number is taken as a hex value and
+    /// the other way around:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    /// use std::io::Cursor;
+    ///
+    /// let mut kconfig = parser::parse_str("config JOBS\n int\nconfig BASE\n hex\n").unwrap();
+    /// kconfig.load_from(Cursor::new("CONFIG_JOBS=0x10\nCONFIG_BASE=4096\n")).unwrap();
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(16)));
+    /// assert_eq!(kconfig.vars["BASE"].value, Some(Value::from(0x1000u64)));
+    ///
+    /// let err = kconfig.load_from(Cursor::new("CONFIG_JOBS=y\n")).unwrap_err();
+    /// assert_eq!(err.to_string(), "line 1: symbol JOBS expects int but got bool");
+    /// assert_eq!(kconfig.vars["JOBS"].value, Some(Value::Int(16)));
+    /// ```
+    ///
+    /// Lines may end in `\r\n` as well as `\n`:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    /// use std::io::Cursor;
+    ///
+    /// let mut kconfig = parser::parse_str("config DEBUG\n bool\nconfig CC\n string\n").unwrap();
+    /// let config = "# CONFIG_DEBUG is not set\r\nCONFIG_CC=\"gcc\"\r\n";
+    /// kconfig.load_from(Cursor::new(config)).unwrap();
+    /// assert_eq!(kconfig.vars["DEBUG"].value, Some(Value::Bool(false)));
+    /// assert_eq!(kconfig.vars["CC"].value, Some(Value::String("gcc".to_string())));
+    ///
+    /// let (_, value) = parser::parse_config_line("CONFIG_CC=\"gcc\"\r").unwrap();
+    /// assert_eq!(value, Value::String("gcc".to_string()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from `r` fails, or if a value is out of
+    /// range for its type, isn't a value at all, or is of the wrong type for its symbol.
+    pub fn load_from<R: Read>(&mut self, r: R) -> io::Result<()> {
+        let reader = BufReader::new(r);
+        for (i, line) in reader.lines().enumerate() {
+            let fail = |e: Error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {e}", i + 1))
+            };
+            let res = parser::read_config_line(&line?).map_err(fail)?;
+            if let Some((k, v)) = res {
+                if let Some(var) = self.vars.get_mut(&k) {
+                    var.value = Some(var.coerce(v).map_err(fail)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a minimal `defconfig` file located at `defconfig`, which only lists the settings
+    /// that differ from their defaults. Any current values are cleared, the file is applied as
+    /// [`load`](KConfig::load) would, and then every variable it didn't mention gets its
+    /// default, as with [`load_default`](KConfig::load_default).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     r#"
+    ///     config SMP
+    ///         bool
+    ///         default y
+    ///     config NR_CPUS
+    ///         int
+    ///         default 8
+    ///     config DEBUG
+    ///         bool
+    ///         default n
+    ///     config CC
+    ///         string
+    ///         default "gcc"
+    ///     "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("konf-load-defconfig-example-{}", std::process::id()));
+    /// std::fs::write(&path, "CONFIG_NR_CPUS=64\nCONFIG_DEBUG=y\n").unwrap();
+    /// kconfig.load_defconfig(path.to_str().unwrap()).unwrap();
+    ///
+    /// assert_eq!(kconfig.vars["NR_CPUS"].value, Some(Value::Int(64)));
+    /// assert_eq!(kconfig.vars["DEBUG"].value, Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.vars["SMP"].value, Some(Value::Bool(true)));
+    /// assert_eq!(kconfig.vars["CC"].value, Some(Value::String("gcc".to_string())));
+    /// ```
+    ///
+    /// Choices which the file doesn't set get their `default` member:
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    ///
+    /// let mut kconfig = parser::parse_str(
+    ///     "choice\n prompt \"cc\"\n default CLANG\nconfig GCC\n bool\nconfig CLANG\n bool\nendchoice\n",
+    /// )
+    /// .unwrap();
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("konf-load-defconfig-choice-{}", std::process::id()));
+    /// std::fs::write(&path, "").unwrap();
+    /// kconfig.load_defconfig(path.to_str().unwrap()).unwrap();
+    /// assert_eq!(kconfig.vars["CLANG"].value, Some(Value::Bool(true)));
+    /// assert!(kconfig.validate().is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file at `defconfig` cannot be read.
+    pub fn load_defconfig(&mut self, defconfig: &str) -> io::Result<()> {
+        self.reset();
+        self.load(defconfig)?;
+        self.resolve_defaults();
+        self.select_choice_defaults();
+        Ok(())
+    }
+}
+
+/// Render the output of [`KConfig::diff`], one variable per line. Changed values are shown as
+/// `NAME old -> new`, while added and removed ones are prefixed with `+` and `-`
+pub fn format_diff(diff: &[(String, Option<Value>, Option<Value>)]) -> String {
+    let mut out = String::new();
+    for (name, old, new) in diff {
+        let line = match (old, new) {
+            (Some(old), Some(new)) => format!("{name} {old} -> {new}"),
+            (None, Some(new)) => format!("+{name} {new}"),
+            (Some(old), None) => format!("-{name} {old}"),
+            (None, None) => continue,
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// An empty `KConfig`, the same as [`KConfig::new`]
+impl Default for KConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error for a menu which doesn't exist
+fn no_menu(name: &str) -> Error {
+    Error::new(ErrorKind::Other, format!("no menu called \"{name}\""))
+}
+
+/// Builds a [`KConfig`] one symbol and menu at a time. Made by [`KConfig::builder`]
+pub struct Builder {
+    /// The menu symbols are currently being added to
+    menu: Menu,
+    vars: IndexMap<String, Variable>,
+}
+
+impl Builder {
+    /// Add a config `name` of type `ty` to the current menu, with `default` as its default if
+    /// it has one
+    pub fn var(mut self, name: &str, ty: Type, default: Option<Value>) -> Self {
+        let mut var = Variable::new(name);
+        var.ty = Some(ty);
+        var.defaults.extend(default.map(|v| (v.into(), None)));
+        self.vars.insert(name.to_string(), var);
+        self.menu.entries.push(Entry::Variable(name.to_string()));
+        self
+    }
+
+    /// Add a menu called `name` to the current menu, with the contents added by `f`
+    pub fn menu(mut self, name: &str, f: impl FnOnce(Self) -> Self) -> Self {
+        let outer = std::mem::replace(&mut self.menu, Menu::new(name));
+        let mut inner = f(self);
+        let menu = std::mem::replace(&mut inner.menu, outer);
+        inner.menu.entries.push(Entry::Menu(menu));
+        inner
+    }
+
+    /// Finish building, with the menus wired up under `root`
+    pub fn build(self) -> KConfig {
+        KConfig {
+            root: self.menu,
+            vars: self.vars,
+            ..KConfig::new()
+        }
+    }
+}
+
+/// Parse Kconfig text with [`parser::parse_str`]
+///
+/// # Examples
+///
+/// ```
+/// use konf::KConfig;
+///
+/// let kconfig: KConfig = "config DEBUG\n bool \"Debug\"\n".parse().unwrap();
+/// assert!(kconfig.vars.contains_key("DEBUG"));
+/// assert_eq!(kconfig.root.entries.len(), 1);
+///
+/// assert_eq!(KConfig::default(), KConfig::new());
+/// assert!("config 123".parse::<KConfig>().is_err());
+/// ```
+impl std::str::FromStr for KConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parser::parse_str(s)
+    }
+}
+
+impl fmt::Display for KConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Formatted {
+            kconfig: self,
+            opts: &FormatOptions::default(),
+            mainmenu: true,
+        }
+        .fmt(f)
+    }
+}
+
+/// A KConfig written out as Kconfig text laid out by some [`FormatOptions`]
+struct Formatted<'a> {
+    kconfig: &'a KConfig,
+    opts: &'a FormatOptions,
+    /// Whether to start with a `mainmenu` line
+    mainmenu: bool,
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mainmenu {
+            writeln!(f, "mainmenu {}", quote(&self.kconfig.name))?;
+            f.write_str("\n")?;
+        }
+        let kconfig = self.kconfig;
+        kconfig.root.pretty_format(f, kconfig, self.opts, 0)?;
         Ok(())
     }
 }
@@ -1,31 +1,113 @@
 #![warn(rust_2018_idioms)]
 
-use clap::Parser;
+use clap::{ArgEnum, Parser};
+use std::process::ExitCode;
+
+/// What to do with the configuration once the Kconfig is parsed
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Print the Kconfig with its default values
+    Print,
+    /// Load the config file, print it and save it back out
+    Load,
+    /// Load the config file and save the minimal defconfig which reproduces it
+    Defconfig,
+    /// Load the config file, bring it up to date with the Kconfig and save it back out
+    Olddefconfig,
+}
+
+impl Mode {
+    /// Where the mode writes to when `--output` isn't given
+    fn default_output(self, config_file: &str) -> &str {
+        match self {
+            Self::Defconfig => "defconfig",
+            _ => config_file,
+        }
+    }
+}
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
+    /// Also write the configuration as a C header of `#define`s to this path
     #[clap(short, long)]
     header: Option<String>,
 
+    /// What to do with the configuration
+    #[clap(short, long, arg_enum, default_value = "load")]
+    mode: Mode,
+
+    /// Where to save the result, if the mode saves one
+    #[clap(short, long)]
+    output: Option<String>,
+
+    /// The `.config` file to load. If it doesn't exist, the defaults are used
+    #[clap(short = 'c', long, default_value = ".config")]
+    config_file: String,
+
     #[clap(default_value = "Kconfig")]
     config: String,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
     let config = konf::parser::parse_file(&args.config);
 
     if let Err(err) = config {
         eprintln!("failed to parse {}: {}", args.config, err);
-        return;
+        return ExitCode::FAILURE;
     }
 
     let mut config = config.unwrap();
     config.load_default();
-    println!("{}", config);
-    config.load(".config").unwrap();
-    println!("{}", config);
-    config.save_config(".config").unwrap();
+    if args.mode == Mode::Print {
+        println!("{}", config);
+        return write_header(&args, &config);
+    }
+
+    match config.load(&args.config_file) {
+        Ok(()) => {}
+        // a fresh tree has no config yet, which is the same as taking the defaults
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            eprintln!("failed to load {}: {}", args.config_file, err);
+            return ExitCode::FAILURE;
+        }
+    }
+    let output = args
+        .output
+        .as_deref()
+        .unwrap_or(args.mode.default_output(&args.config_file));
+    let saved = match args.mode {
+        Mode::Print => unreachable!(),
+        Mode::Load => {
+            println!("{}", config);
+            config.save_config(output)
+        }
+        Mode::Defconfig => config.save_defconfig(output),
+        Mode::Olddefconfig => match config.evaluate() {
+            Ok(()) => config.save_config(output),
+            Err(err) => {
+                eprintln!("failed to update the configuration: {}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    if let Err(err) = saved {
+        eprintln!("failed to write {}: {}", output, err);
+        return ExitCode::FAILURE;
+    }
+    write_header(&args, &config)
+}
+
+/// Write the `--header` file, if one was asked for
+fn write_header(args: &Args, config: &konf::KConfig) -> ExitCode {
+    if let Some(header) = &args.header {
+        if let Err(err) = std::fs::write(header, config.to_c_header()) {
+            eprintln!("failed to write {}: {}", header, err);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
 }
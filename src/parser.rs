@@ -1,12 +1,98 @@
+//! Parsing of Kconfig files, and of the lines of `.config` files.
+//!
+//! `#` starts a comment which runs to the end of the line:
+//!
+//! ```
+//! use konf::parser;
+//!
+//! let kconfig = parser::parse_str(
+//!     r#"
+//!     ## Compiler selection
+//!     config GCC
+//!         bool "Use GCC" # the default
+//!         default y
+//!     ## config CLANG
+//!     config DEBUG
+//!         bool
+//!     "#,
+//! )
+//! .unwrap();
+//! assert_eq!(kconfig.vars.keys().collect::<Vec<_>>(), ["GCC", "DEBUG"]);
+//! assert_eq!(kconfig.vars["GCC"].desc.as_deref(), Some("Use GCC"));
+//! ```
+//!
+//! A line ending in `\` carries on onto the next one. Help text is taken as it is written, so
+//! a `\` there is kept:
+//!
+//! ```
+//! use konf::parser;
+//!
+//! let kconfig = parser::parse_str(
+//!     "config A\n bool\nconfig B\n bool\nconfig C\n bool\n depends on A && \\\n   B\n help\n  Needs A \\\n  and B.\n",
+//! )
+//! .unwrap();
+//! let c = &kconfig.vars["C"];
+//! assert_eq!(c.depends.as_ref().unwrap().to_string(), "A && B");
+//! assert_eq!(c.help.as_deref(), Some("Needs A \\\nand B."));
+//! ```
+//!
+//! Files with Windows line endings parse the same as any other:
+//!
+//! ```
+//! use konf::parser;
+//!
+//! let text = "config CC\n string \"Compiler\" # default\n default \"gcc\"\n help\n  Which compiler.\n";
+//! let kconfig = parser::parse_str(&text.replace('\n', "\r\n")).unwrap();
+//! assert_eq!(kconfig, parser::parse_str(text).unwrap());
+//! ```
+
 use super::*;
 use logos::{Lexer, Logos};
-use std::path::Path;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 fn string_tokenize<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<&'a str> {
     let slice = lex.slice();
     Some(&slice[1..slice.len() - 1])
 }
 
+/// Undo the `\` escapes in the text of a string token, so `\"` is `"` and `\\` is `\`
+fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' {
+            chars.next().unwrap_or(c)
+        } else {
+            c
+        });
+    }
+    Cow::Owned(out)
+}
+
+/// Parse an int, which may be written in binary (`0b`) or octal (`0o`) as well as decimal
+pub(crate) fn parse_int(slice: &str) -> Option<i64> {
+    let (sign, digits) = match slice.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", slice),
+    };
+    let (radix, digits) = if let Some(digits) = digits.strip_prefix("0b") {
+        (2, digits)
+    } else if let Some(digits) = digits.strip_prefix("0o") {
+        (8, digits)
+    } else {
+        (10, digits)
+    };
+    // keep the sign on the digits, so that `i64::MIN` still fits
+    i64::from_str_radix(&format!("{sign}{digits}"), radix).ok()
+}
+
 #[derive(Logos, Debug, PartialEq, Copy, Clone)]
 enum Token<'a> {
     #[token("mainmenu")]
@@ -20,11 +106,58 @@ enum Token<'a> {
     #[token("endmenu")]
     EndMenu,
 
+    #[token("choice")]
+    Choice,
+    #[token("endchoice")]
+    EndChoice,
+    #[token("prompt")]
+    Prompt,
+    #[token("optional")]
+    Optional,
+
     #[token("config")]
     Config,
 
     #[token("default")]
     Default,
+    #[token("def_bool")]
+    DefBool,
+    #[token("def_tristate")]
+    DefTristate,
+
+    #[token("range")]
+    Range,
+
+    // older files spell it `---help---`
+    #[token("help")]
+    #[token("---help---")]
+    Help,
+
+    #[token("select")]
+    Select,
+    #[token("imply")]
+    Imply,
+
+    #[token("depends")]
+    Depends,
+    #[token("on")]
+    On,
+
+    #[token("if")]
+    If,
+
+    #[token("&&")]
+    And,
+    #[token("||")]
+    Or,
+    #[token("!")]
+    Not,
+    #[token("!=")]
+    NotEquals,
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
 
     #[token("y")]
     Yes,
@@ -35,33 +168,284 @@ enum Token<'a> {
     #[token("=")]
     Equals,
 
-    #[regex("[A-Z_]+")]
+    #[regex("[A-Z_][A-Z0-9_]*")]
     Name(&'a str),
 
-    #[regex("\"([[^\"].]+)\"", string_tokenize)]
+    #[regex("-?(0b[01]+|0o[0-7]+|[0-9]+)")]
+    Int(&'a str),
+
+    #[regex("0x[0-9a-fA-F]+")]
+    Hex(&'a str),
+
+    #[regex(r#""([^"\\]|\\.)*""#, string_tokenize)]
     String(&'a str),
 
-    #[regex("(bool|int|string)", |lex| super::Type::new(lex.slice()))]
+    #[regex("(bool|int|hex|string)", |lex| super::Type::new(lex.slice()))]
     Type(Type),
 
+    // Comments run to the end of the line. The parser drops them unless they are being kept
+    #[regex(r"#[^\n]*", |lex| lex.slice()[1..].trim())]
+    Comment(&'a str),
+
     // Logos requires one token variant to handle errors,
     // it can be named anything you wish.
     #[error]
     // We can also use this variant to define whitespace,
-    // or any other matches we wish to skip.
-    #[regex(r"[ \t\n\f]+", logos::skip)]
+    // or any other matches we wish to skip. A `\` at the end of a line joins it to the next.
+    #[regex(r"([ \t\r\n\f]|\\\r?\n)+", logos::skip)]
     Error,
 }
 
+/// The kind of a token produced by [`tokenize`]. Its text can be recovered from the span.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+pub enum TokenKind {
+    MainMenu,
+    Source,
+    Menu,
+    EndMenu,
+    Choice,
+    EndChoice,
+    Prompt,
+    Optional,
+    Config,
+    Default,
+    DefBool,
+    DefTristate,
+    Range,
+    Help,
+    Select,
+    Imply,
+    Depends,
+    On,
+    If,
+    And,
+    Or,
+    Not,
+    NotEquals,
+    LParen,
+    RParen,
+    Yes,
+    No,
+    Equals,
+    /// A symbol name
+    Name,
+    Int,
+    Hex,
+    /// A quoted string, including the quotes
+    String,
+    /// One of the type keywords, like `bool`
+    Type,
+    Comment,
+    /// Text which isn't any kind of token
+    Error,
+}
+
+impl Token<'_> {
+    fn kind(self) -> TokenKind {
+        match self {
+            Self::MainMenu => TokenKind::MainMenu,
+            Self::Source => TokenKind::Source,
+            Self::Menu => TokenKind::Menu,
+            Self::EndMenu => TokenKind::EndMenu,
+            Self::Choice => TokenKind::Choice,
+            Self::EndChoice => TokenKind::EndChoice,
+            Self::Prompt => TokenKind::Prompt,
+            Self::Optional => TokenKind::Optional,
+            Self::Config => TokenKind::Config,
+            Self::Default => TokenKind::Default,
+            Self::DefBool => TokenKind::DefBool,
+            Self::DefTristate => TokenKind::DefTristate,
+            Self::Range => TokenKind::Range,
+            Self::Help => TokenKind::Help,
+            Self::Select => TokenKind::Select,
+            Self::Imply => TokenKind::Imply,
+            Self::Depends => TokenKind::Depends,
+            Self::On => TokenKind::On,
+            Self::If => TokenKind::If,
+            Self::And => TokenKind::And,
+            Self::Or => TokenKind::Or,
+            Self::Not => TokenKind::Not,
+            Self::NotEquals => TokenKind::NotEquals,
+            Self::LParen => TokenKind::LParen,
+            Self::RParen => TokenKind::RParen,
+            Self::Yes => TokenKind::Yes,
+            Self::No => TokenKind::No,
+            Self::Equals => TokenKind::Equals,
+            Self::Name(_) => TokenKind::Name,
+            Self::Int(_) => TokenKind::Int,
+            Self::Hex(_) => TokenKind::Hex,
+            Self::String(_) => TokenKind::String,
+            Self::Type(_) => TokenKind::Type,
+            Self::Comment(_) => TokenKind::Comment,
+            Self::Error => TokenKind::Error,
+        }
+    }
+}
+
+/// Split Kconfig text into tokens, each with the byte range of `text` it covers, for tools
+/// like syntax highlighters. Whitespace is skipped, but comments are kept. Help text isn't
+/// treated specially, so it comes out as whatever tokens it happens to look like.
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser::{self, TokenKind};
+///
+/// let text = "config JOBS\n    int \"Jobs\" # parallel\n";
+/// let tokens: Vec<_> = parser::tokenize(text).collect();
+/// assert_eq!(
+///     tokens,
+///     [
+///         (TokenKind::Config, 0..6),
+///         (TokenKind::Name, 7..11),
+///         (TokenKind::Type, 16..19),
+///         (TokenKind::String, 20..26),
+///         (TokenKind::Comment, 27..37),
+///     ]
+/// );
+/// assert_eq!(&text[20..26], "\"Jobs\"");
+/// ```
+pub fn tokenize(text: &str) -> impl Iterator<Item = (TokenKind, Range<usize>)> + '_ {
+    Token::lexer(text)
+        .spanned()
+        .map(|(tok, span)| (tok.kind(), span))
+}
+
+/// Options controlling how Kconfig files are parsed
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser::{self, Options};
+///
+/// let dir = std::env::temp_dir().join(format!("konf-options-example-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("Kconfig.arch"), "config FOO\n int\n").unwrap();
+/// std::fs::write(
+///     dir.join("Kconfig"),
+///     "config FOO\n bool\n\nsource \"Kconfig.arch\"\n",
+/// )
+/// .unwrap();
+///
+/// // by default the second definition extends the first, with a warning giving both places
+/// let (kconfig, warnings) = parser::parse_file_with_warnings(dir.join("Kconfig")).unwrap();
+/// assert_eq!(kconfig.vars["FOO"].ty, Some(konf::Type::Int));
+/// assert_eq!(warnings.len(), 1);
+/// assert!(warnings[0].location.ends_with("Kconfig.arch:1"));
+/// assert!(warnings[0].msg.starts_with("duplicate definition of `FOO`"));
+/// assert!(warnings[0].msg.ends_with("Kconfig:1"));
+///
+/// let options = Options { deny_duplicates: true, ..Default::default() };
+/// let err = parser::parse_file_with_options(dir.join("Kconfig"), &options).unwrap_err();
+/// let msg = err.to_string();
+/// assert!(msg.contains("Kconfig.arch:1: duplicate definition of `FOO`"));
+/// assert!(msg.ends_with("Kconfig:1"));
+/// ```
+///
+/// Text the lexer doesn't recognize is skipped with a warning, unless parsing is `strict`:
+///
+/// ```
+/// use konf::parser::{self, Options};
+///
+/// let path = std::env::temp_dir().join(format!("konf-options-strict-{}", std::process::id()));
+/// std::fs::write(&path, "config FOO\n bool\n@\nconfig BAR\n bool\n").unwrap();
+///
+/// let (kconfig, warnings) = parser::parse_file_with_warnings(&path).unwrap();
+/// assert!(kconfig.vars.contains_key("BAR"));
+/// assert_eq!(warnings[0].msg, "skipped unrecognized text `@`");
+///
+/// let options = Options { strict: true, ..Default::default() };
+/// let err = parser::parse_file_with_options(&path, &options).unwrap_err();
+/// assert!(err.to_string().ends_with(":3: unrecognized text `@`"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Error on `$(VAR)` references to unset environment variables, rather than expanding them
+    /// to an empty string
+    pub strict_env: bool,
+    /// Error when a `config` is defined more than once, rather than letting the later
+    /// definitions extend the first
+    pub deny_duplicates: bool,
+    /// Keep `#` comments as [`Entry::SourceComment`]s in the menu tree, rather than dropping
+    /// them
+    pub keep_comments: bool,
+    /// Directories to look in, in order, for a `source` target which isn't found relative to
+    /// the file sourcing it
+    pub search_dirs: Vec<PathBuf>,
+    /// How deeply `source`s may be nested before parsing fails. Defaults to 64
+    pub max_source_depth: usize,
+    /// Error on text the lexer doesn't recognize, rather than skipping it with a warning
+    pub strict: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            strict_env: false,
+            deny_duplicates: false,
+            keep_comments: false,
+            search_dirs: vec![],
+            max_source_depth: 64,
+            strict: false,
+        }
+    }
+}
+
+/// A problem found while parsing which didn't stop the parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Where the problem is, as `path:line`
+    pub location: String,
+    pub msg: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.msg)
+    }
+}
+
+/// Parser state which carries across into sourced files
+#[derive(Default)]
+struct State {
+    /// Where each symbol has been defined so far
+    defined: HashMap<String, String>,
+    warnings: Vec<Warning>,
+    /// Errors which were recovered from
+    errors: Vec<Error>,
+    /// The title given by `mainmenu`, if there has been one
+    mainmenu: Option<String>,
+    /// The configs declared so far in the `choice` being parsed, if there is one
+    members: Option<Vec<String>>,
+}
+
 struct Parser<'a> {
-    toks: std::iter::Peekable<Lexer<'a, Token<'a>>>,
+    text: &'a str,
+    toks: std::iter::Peekable<logos::SpannedIter<'a, Token<'a>>>,
+    /// The byte range of the last token taken
+    span: Range<usize>,
+    /// Where in `text` the lexer started, which its spans are relative to
+    offset: usize,
+    /// The bytes and lines of the input before `text`, when it is one piece of a larger input
+    origin: (usize, usize),
+    /// The file being parsed, if there is one
+    path: Option<PathBuf>,
+    /// The files sourced to get to this one, outermost first
+    sources: Vec<String>,
+    options: Options,
+    /// Record errors and skip to the next entry rather than stopping the parse
+    recover: bool,
+    /// Comments passed over since the last entry, when `keep_comments` is on
+    comments: Vec<String>,
+    state: State,
 }
 
 macro_rules! accept {
     ($method:ident, $variant:ident, $t:ty) => {
         fn $method(&mut self) -> Option<$t> {
-            if let Some(&Token::$variant(x)) = self.toks.peek() {
-                self.toks.next();
+            if let Some(Token::$variant(x)) = self.peek() {
+                self.next();
                 Some(x)
             } else {
                 None
@@ -72,173 +456,1178 @@ macro_rules! accept {
 
 impl<'a> Parser<'a> {
     pub fn new(text: &'a str) -> Self {
+        Self::with_options(text, Options::default())
+    }
+
+    pub fn with_options(text: &'a str, options: Options) -> Self {
         Self {
-            toks: Token::lexer(text).peekable(),
+            text,
+            toks: Token::lexer(text).spanned().peekable(),
+            span: 0..0,
+            offset: 0,
+            origin: (0, 0),
+            path: None,
+            sources: vec![],
+            options,
+            recover: false,
+            comments: vec![],
+            state: State::default(),
         }
     }
 
     pub fn next(&mut self) -> Option<Token<'a>> {
-        self.toks.next()
+        self.skip_comments();
+        let (tok, span) = self.toks.next()?;
+        self.span = span.start + self.offset..span.end + self.offset;
+        Some(tok)
     }
 
     pub fn peek(&mut self) -> Option<Token<'a>> {
-        self.toks.peek().copied()
+        self.skip_comments();
+        self.toks.peek().map(|(tok, _)| *tok)
     }
 
+    /// Pass over any comments coming up, holding on to them if they are being kept
+    fn skip_comments(&mut self) {
+        while let Some((Token::Comment(c), _)) = self.toks.peek() {
+            if self.options.keep_comments {
+                self.comments.push(c.to_string());
+            }
+            self.toks.next();
+        }
+    }
+
+    /// Add the comments passed over so far to `menu`
+    fn flush_comments(&mut self, menu: &mut Menu) {
+        let comments = self.comments.drain(..).map(Entry::SourceComment);
+        menu.entries.extend(comments);
+    }
+
+    /// Describe where the last token taken is, as `path:line`
+    pub fn location(&self) -> String {
+        let line = self.text[..self.span.start].matches('\n').count() + self.origin.1 + 1;
+        match &self.path {
+            Some(path) => format!("{}:{line}", path.display()),
+            None => format!("line {line}"),
+        }
+    }
+
+    /// The span in the whole input from `start` to the end of the last token taken
+    fn span_from(&self, start: usize) -> Range<usize> {
+        start + self.origin.0..self.span.end + self.origin.0
+    }
+
+    /// Skip ahead to the next top level keyword, to recover from an error in the current entry
+    pub fn skip_entry(&mut self) {
+        while let Some(tok) = self.peek() {
+            if let Token::MainMenu
+            | Token::Menu
+            | Token::EndMenu
+            | Token::Choice
+            | Token::EndChoice
+            | Token::Config
+            | Token::Source = tok
+            {
+                break;
+            }
+            self.next();
+        }
+    }
+
+    /// Record a warning at the location of the last token taken
+    pub fn warn(&mut self, msg: impl Into<String>) {
+        let location = self.location();
+        self.state.warnings.push(Warning {
+            location,
+            msg: msg.into(),
+        });
+    }
+
+    /// Consume the next token if it is `tok`
+    pub fn accept(&mut self, tok: Token<'a>) -> bool {
+        if self.peek() == Some(tok) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    accept!(accept_name, Name, &'a str);
     accept!(accept_string, String, &'a str);
     accept!(accept_type, Type, Type);
 
-    pub fn parse_value(&mut self) -> Option<Value> {
-        let val = self.peek();
-        if let Some(val) = val {
-            match val {
-                Token::Yes => {
+    /// Parse one end of a `range`, which is either a value or a symbol
+    fn parse_bound(&mut self) -> Result<Option<DefaultKind>> {
+        if let Some(name) = self.accept_name() {
+            return Ok(Some(DefaultKind::Symbol(name.to_string())));
+        }
+        Ok(self.parse_value()?.map(DefaultKind::Literal))
+    }
+
+    /// Parse the argument of a `default`, which is either a symbol or a value. Strings have
+    /// `$(VAR)` references expanded
+    fn parse_default(&mut self, keyword: &str) -> Result<DefaultKind> {
+        if let Some(name) = self.accept_name() {
+            return Ok(DefaultKind::Symbol(name.to_string()));
+        }
+        match self.parse_value()? {
+            Some(Value::String(s)) => Ok(Value::String(self.expand_env(&s)?).into()),
+            Some(val) => Ok(val.into()),
+            None => {
+                let msg = format!("Missing argument for `{keyword}`");
+                Err(Error::new(ErrorKind::Parse, msg))
+            }
+        }
+    }
+
+    /// Parse the properties of a `config` into `var`. They can come in any order; those which
+    /// can only be given once, like the type, take the last one given.
+    fn parse_properties(&mut self, var: &mut Variable) -> Result<()> {
+        loop {
+            match self.peek() {
+                // the type, with an optional prompt after it
+                Some(Token::Type(t)) => {
                     self.next();
-                    return Some(Value::Bool(true));
+                    var.ty = Some(t);
+                    if let Some(s) = self.accept_string() {
+                        var.desc = Some(unescape(s).into_owned());
+                    }
                 }
-                Token::No => {
+
+                // "default" VALUE|NAME ["if" EXPR]
+                Some(Token::Default) => {
                     self.next();
-                    return Some(Value::Bool(false));
+                    let default = self.parse_default("default")?;
+                    var.defaults.push((default, self.parse_condition()?));
                 }
-                _ => return None,
+
+                // "def_bool" VALUE|NAME ["if" EXPR], a `bool` with a default in one go
+                Some(tok @ (Token::DefBool | Token::DefTristate)) => {
+                    self.next();
+                    if tok == Token::DefTristate {
+                        self.warn(
+                            "`def_tristate` is read as `def_bool`, as there are no tristates",
+                        );
+                    }
+                    var.ty = Some(Type::Bool);
+                    let default = self.parse_default("def_bool")?;
+                    var.defaults.push((default, self.parse_condition()?));
+                }
+
+                // "select"/"imply" NAME ["if" EXPR]
+                Some(tok @ (Token::Select | Token::Imply)) => {
+                    self.next();
+                    let target = self
+                        .accept_name()
+                        .ok_or("Expected a symbol after `select` or `imply`")?;
+                    let cond = self.parse_condition()?;
+                    let list = if tok == Token::Select {
+                        &mut var.selects
+                    } else {
+                        &mut var.implies
+                    };
+                    list.push((target.to_string(), cond));
+                }
+
+                // "depends on" EXPR
+                Some(Token::Depends) => {
+                    self.next();
+                    if !self.accept(Token::On) {
+                        return Err("Expected `on` after `depends`".into());
+                    }
+                    // each further `depends on` must hold as well
+                    let e = self.parse_expr()?;
+                    var.depends = Some(match var.depends.take() {
+                        Some(prev) => Expr::And(Box::new(prev), Box::new(e)),
+                        None => e,
+                    });
+                }
+
+                // "range" MIN MAX
+                Some(Token::Range) => {
+                    self.next();
+                    match (self.parse_bound()?, self.parse_bound()?) {
+                        (Some(min), Some(max)) => var.range = Some((min, max)),
+                        _ => return Err("Missing arguments for `range`".into()),
+                    }
+                }
+
+                // "help", then the indented text on the lines below it
+                Some(Token::Help) => {
+                    self.next();
+                    var.help = Some(self.take_help());
+                }
+
+                _ => return Ok(()),
             }
         }
-        return None;
+    }
+
+    /// Parse an optional `if EXPR` condition
+    fn parse_condition(&mut self) -> Result<Option<Expr>> {
+        if self.accept(Token::If) {
+            Ok(Some(self.parse_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Take the block of help text starting on the line after the last token, and carry on
+    /// lexing after it. The block runs until the first line which isn't blank and is indented
+    /// less than the block's first line, so blank lines between paragraphs are kept. The common
+    /// indentation is removed.
+    fn take_help(&mut self) -> String {
+        let after = self.span.end;
+        let Some(newline) = self.text[after..].find('\n') else {
+            return String::new();
+        };
+        let mut pos = after + newline + 1;
+        let mut indent = None;
+        let mut help = vec![];
+        for line in self.text[pos..].split_inclusive('\n') {
+            let text = line.trim_end();
+            let depth = text.len() - text.trim_start().len();
+            if text.is_empty() {
+                help.push("");
+            } else if depth == 0 || depth < *indent.get_or_insert(depth) {
+                break;
+            } else {
+                help.push(&text[indent.unwrap_or(depth)..]);
+            }
+            pos += line.len();
+        }
+        self.toks = Token::lexer(&self.text[pos..]).spanned().peekable();
+        self.offset = pos;
+        help.join("\n").trim_matches('\n').to_string()
+    }
+
+    /// Parse a value if one comes next. Ints and hex values which don't fit in 64 bits are an
+    /// error
+    pub fn parse_value(&mut self) -> Result<Option<Value>> {
+        let val = match self.peek() {
+            Some(Token::Yes) => Value::Bool(true),
+            Some(Token::No) => Value::Bool(false),
+            Some(Token::Int(i)) => match parse_int(i) {
+                Some(i) => Value::Int(i),
+                None => {
+                    let msg = format!("int `{i}` is out of range");
+                    return Err(Error::new(ErrorKind::OutOfRange, msg));
+                }
+            },
+            Some(Token::Hex(h)) => match Hex::parse(h) {
+                Some(h) => Value::Hex(h),
+                None => {
+                    let msg = format!("hex value `{h}` is out of range");
+                    return Err(Error::new(ErrorKind::OutOfRange, msg));
+                }
+            },
+            Some(Token::String(s)) => Value::String(unescape(s).into_owned()),
+            _ => return Ok(None),
+        };
+        self.next();
+        Ok(Some(val))
+    }
+
+    /// Parse an expression. From loosest to tightest binding, the operators are `||`, `&&`, `=`
+    /// and `!=`, then `!`
+    pub fn parse_expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.parse_and()?;
+        while self.accept(Token::Or) {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.parse_and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut lhs = self.parse_unary()?;
+        while self.accept(Token::And) {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.parse_unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.accept(Token::Not) {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.accept(Token::LParen) {
+            let e = self.parse_expr()?;
+            if !self.accept(Token::RParen) {
+                return Err("Missing `)` in expression".into());
+            }
+            return Ok(e);
+        }
+
+        let lhs = self.parse_atom()?;
+        if self.accept(Token::Equals) {
+            Ok(Expr::Eq(Box::new(lhs), Box::new(self.parse_atom()?)))
+        } else if self.accept(Token::NotEquals) {
+            Ok(Expr::Ne(Box::new(lhs), Box::new(self.parse_atom()?)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr> {
+        if let Some(name) = self.accept_name() {
+            Ok(Expr::Symbol(name.to_string()))
+        } else if let Some(val) = self.parse_value()? {
+            Ok(Expr::Const(val))
+        } else {
+            Err("Expected a symbol or a value in expression".into())
+        }
+    }
+
+    /// Find the file a `source` of `target` refers to. Sources are relative to the directory of
+    /// the current kconfig, falling back to each of the search directories in turn, unless they
+    /// are absolute
+    fn find_source(&self, base: Option<&Path>, target: &str) -> Result<PathBuf> {
+        if Path::new(target).is_absolute() {
+            return Ok(PathBuf::from(target));
+        }
+        let mut candidates = base
+            .into_iter()
+            .chain(self.options.search_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(target));
+        let first = candidates
+            .next()
+            .ok_or("`source` needs a base directory to resolve from")?;
+        if first.exists() {
+            return Ok(first);
+        }
+        // if it can't be found anywhere, the error is about the first place it should be
+        Ok(candidates.find(|path| path.exists()).unwrap_or(first))
+    }
+
+    /// Expand any `$(VAR)` references in `s` from the environment
+    pub fn expand_env(&self, s: &str) -> Result<String> {
+        static VAR_REF: OnceLock<Regex> = OnceLock::new();
+        let var_ref = VAR_REF.get_or_init(|| Regex::new(r"\$\(([A-Za-z0-9_]+)\)").unwrap());
+        let mut out = String::new();
+        let mut last = 0;
+        for caps in var_ref.captures_iter(s) {
+            let whole = caps.get(0).unwrap();
+            out.push_str(&s[last..whole.start()]);
+            match std::env::var(&caps[1]) {
+                Ok(val) => out.push_str(&val),
+                Err(_) if self.options.strict_env => {
+                    let msg = format!("environment variable `{}` is not set", &caps[1]);
+                    return Err(Error::new(ErrorKind::Parse, msg));
+                }
+                Err(_) => {}
+            }
+            last = whole.end();
+        }
+        out.push_str(&s[last..]);
+        Ok(out)
+    }
+}
+
+/// The keyword which closes a block, for messages
+fn keyword(tok: Token<'_>) -> &'static str {
+    match tok {
+        Token::EndMenu => "endmenu",
+        _ => "endchoice",
     }
 }
 
 impl Menu {
+    /// Parse entries into this menu up to `end`, the `endmenu` or `endchoice` which closes it,
+    /// or up to the end of the text if it is `None`
     fn parse<'a>(
         &mut self,
-        path: &Path,
+        base: Option<&Path>,
         toks: &mut Parser<'a>,
         vars: &mut IndexMap<String, Variable>,
-    ) -> std::result::Result<(), &'static str> {
+        end: Option<Token<'a>>,
+    ) -> Result<()> {
         while let Some(tok) = toks.next() {
-            // top level options:
-            //    MainMenu
-            //    Config
-            // TODO:
-            //    Include
-            //
-            match tok {
-                // "mainmenu"
-                Token::MainMenu => {
-                    let name = toks.next();
-                    match name {
-                        Some(Token::String(name)) => self.name = name.to_string(),
-                        _ => return Err("Invalid option to `mainmenu`"),
-                    };
+            // comments belong with the entry which follows them
+            toks.flush_comments(self);
+            let res = match self.parse_entry(tok, base, toks, vars) {
+                Ok(true) if Some(tok) == end => {
+                    toks.flush_comments(self);
+                    return Ok(());
+                }
+                Ok(true) => Err(match end {
+                    Some(end) => {
+                        let msg = format!("expected `{}`, found `{}`", keyword(end), keyword(tok));
+                        Error::new(ErrorKind::Parse, msg)
+                    }
+                    None => {
+                        let opener = if tok == Token::EndMenu {
+                            "menu"
+                        } else {
+                            "choice"
+                        };
+                        let msg = format!("`{}` without a matching `{opener}`", keyword(tok));
+                        Error::new(ErrorKind::Parse, msg)
+                    }
+                }),
+                res => res,
+            };
+            match res {
+                Ok(_) => {}
+                Err(e) if toks.recover => {
+                    let location = toks.location();
+                    toks.state.errors.push(e.context(location));
+                    toks.skip_entry();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        toks.flush_comments(self);
+        match end {
+            Some(Token::EndMenu) => {
+                let msg = format!("menu \"{}\" is missing its `endmenu`", self.name);
+                Err(Error::new(ErrorKind::Parse, msg))
+            }
+            Some(_) => Err(Error::new(
+                ErrorKind::Parse,
+                "`choice` is missing its `endchoice`",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse the entry starting at `tok` into this menu, returning whether it ended the menu
+    fn parse_entry<'a>(
+        &mut self,
+        tok: Token<'a>,
+        base: Option<&Path>,
+        toks: &mut Parser<'a>,
+        vars: &mut IndexMap<String, Variable>,
+    ) -> Result<bool> {
+        // top level options:
+        //    MainMenu
+        //    Config
+        // TODO:
+        //    Include
+        //
+        match tok {
+            // "mainmenu"
+            Token::MainMenu => {
+                let name = match toks.next() {
+                    Some(Token::String(name)) => toks.expand_env(&unescape(name))?,
+                    _ => return Err("Invalid option to `mainmenu`".into()),
+                };
+                // a second title is most likely the result of a bad merge
+                if let Some(first) = &toks.state.mainmenu {
+                    let msg = format!("second `mainmenu` \"{name}\", already titled \"{first}\"");
+                    return Err(Error::new(ErrorKind::Parse, msg));
                 }
+                toks.state.mainmenu = Some(name);
+            }
 
-                Token::Menu => {
-                    if let Some(s) = toks.accept_string() {
-                        let mut m = Menu::new(s);
-                        m.parse(path, toks, vars)?;
-                        self.entries.push(Entry::Menu(m));
+            Token::Menu => {
+                let start = toks.span.start;
+                if let Some(s) = toks.accept_string() {
+                    let s = unescape(s);
+                    let mut m = Menu::new(&s);
+                    m.span = Some(toks.span_from(start));
+                    m.parse(base, toks, vars, Some(Token::EndMenu))?;
+                    if m.entries.is_empty() {
+                        toks.warn(format!("menu \"{s}\" is empty"));
                     }
+                    self.entries.push(Entry::Menu(m));
                 }
-                Token::EndMenu => {
-                    // consume the endmenu
+            }
+            Token::EndMenu | Token::EndChoice => {
+                // consume the endmenu or endchoice
+                return Ok(true);
+            }
+
+            // "choice", its properties, then the member configs up to "endchoice"
+            Token::Choice => {
+                let mut choice = Choice::default();
+                loop {
+                    if toks.accept_type().is_some() || toks.accept(Token::Prompt) {
+                        if let Some(s) = toks.accept_string() {
+                            choice.prompt = Some(unescape(s).into_owned());
+                        }
+                        continue;
+                    }
+                    if toks.accept(Token::Optional) {
+                        choice.optional = true;
+                        continue;
+                    }
+                    if toks.accept(Token::Default) {
+                        let name = toks
+                            .accept_name()
+                            .ok_or("Expected a symbol after `default` in a `choice`")?;
+                        choice.default = Some(name.to_string());
+                        continue;
+                    }
                     break;
                 }
 
-                // "config" NAME
-                Token::Config => {
-                    // get the NAME
-                    match toks.next() {
-                        Some(Token::Name(name)) => {
-                            let mut var = Variable::new(name);
-                            loop {
-                                // capture the type of the variable
-                                if let Some(t) = toks.accept_type() {
-                                    var.ty = Some(t);
-                                    // Capture the optional description after the type
-                                    if let Some(s) = toks.accept_string() {
-                                        var.desc = Some(s.to_string());
-                                    }
-                                    continue;
-                                }
-
-                                if let Some(Token::Default) = toks.peek() {
-                                    toks.next();
-
-                                    if let Some(val) = toks.parse_value() {
-                                        var.default = Some(val);
-                                    } else {
-                                        return Err("Missing argument for `default`");
-                                    }
-                                }
-
-                                break;
+                // the members are the configs declared in the body, including any declared
+                // before, which aren't bound into the body again
+                let outer = toks.state.members.replace(vec![]);
+                let mut body = Menu::new("");
+                let res = body.parse(base, toks, vars, Some(Token::EndChoice));
+                choice.members =
+                    std::mem::replace(&mut toks.state.members, outer).unwrap_or_default();
+                res?;
+                for ent in body.entries {
+                    if let Entry::Menu(_) | Entry::Choice(_) = ent {
+                        return Err("only `config` entries may appear in a `choice`".into());
+                    }
+                }
+                if let Some(default) = &choice.default {
+                    if !choice.members.contains(default) {
+                        toks.warn(format!(
+                            "choice default `{default}` is not one of its members"
+                        ));
+                    }
+                }
+                self.entries.push(Entry::Choice(choice));
+            }
+
+            // "config" NAME
+            Token::Config => {
+                let start = toks.span.start;
+                // get the NAME
+                match toks.next() {
+                    Some(Token::Name(name)) => {
+                        let here = toks.location();
+                        if let Some(first) = toks.state.defined.get(name) {
+                            let msg = format!(
+                                "duplicate definition of `{name}`, first defined at {first}"
+                            );
+                            if toks.options.deny_duplicates {
+                                return Err(Error::new(ErrorKind::Parse, format!("{here}: {msg}")));
+                            }
+                            toks.warn(msg);
+                        } else {
+                            toks.state.defined.insert(name.to_string(), here);
+                        }
+                        if let Some(members) = &mut toks.state.members {
+                            if !members.iter().any(|m| m == name) {
+                                members.push(name.to_string());
                             }
+                        }
 
-                            vars.insert(var.name.clone(), var);
+                        // declaring a config again adds to it: `depends on` are ANDed together,
+                        // defaults and selects are appended, and other properties replaced. It
+                        // stays where it was first declared in the menu tree
+                        let declared = vars.contains_key(name);
+                        let span = toks.span_from(start);
+                        let var = vars.entry(name.to_string()).or_insert_with(|| {
+                            let mut var = Variable::new(name);
+                            var.span = Some(span);
+                            var
+                        });
+                        toks.parse_properties(var)?;
+
+                        if !declared {
                             self.entries.push(Entry::Variable(name.to_string()));
                         }
-                        _ => return Err("Invalid name for `config`"),
-                    };
-                }
+                    }
+                    _ => return Err("Invalid name for `config`".into()),
+                };
+            }
 
-                // "source" STRING
-                Token::Source => {
-                    if let Some(s) = toks.accept_string() {
-                        // get the parent path of the current kconfig
-                        let target = path.canonicalize().unwrap().parent().unwrap().join(s);
-                        let other = parse_file(target)?;
-                        // TOAD: merge the menu bro
-                        vars.extend(other.vars);
-                    } else {
-                        return Err("invalid argument to `source`");
+            // "source" STRING
+            Token::Source => {
+                if let Some(s) = toks.accept_string() {
+                    let target = toks.find_source(base, &toks.expand_env(&unescape(s))?)?;
+                    let (text, target) = read_file(&target)?;
+
+                    let mut sources = toks.sources.clone();
+                    sources.push(match &toks.path {
+                        Some(path) => path.display().to_string(),
+                        None => "<input>".to_string(),
+                    });
+                    if sources.len() > toks.options.max_source_depth {
+                        sources.push(target.display().to_string());
+                        let msg = format!(
+                            "`source` nested more than {} deep: {}",
+                            toks.options.max_source_depth,
+                            sources.join(" -> ")
+                        );
+                        return Err(Error::new(ErrorKind::Other, msg));
                     }
+
+                    // the sourced file is parsed in place, as if its text was written here
+                    let mut sub = Parser::with_options(&text, toks.options.clone());
+                    sub.path = Some(target.clone());
+                    sub.sources = sources;
+                    sub.recover = toks.recover;
+                    sub.state = std::mem::take(&mut toks.state);
+                    let res = self.parse(target.parent(), &mut sub, vars, None);
+                    toks.state = sub.state;
+                    res?;
+                } else {
+                    return Err("invalid argument to `source`".into());
+                }
+            }
+            Token::Error => {
+                let text = &toks.text[toks.span.clone()];
+                if toks.options.strict {
+                    let msg = format!("{}: unrecognized text `{text}`", toks.location());
+                    return Err(Error::new(ErrorKind::Parse, msg));
                 }
-                _ => return Err("invalid top level token"),
+                toks.warn(format!("skipped unrecognized text `{text}`"));
             }
+            _ => return Err("invalid top level token".into()),
+        }
+        Ok(false)
+    }
+}
+
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<KConfig> {
+    parse_file_with_options(path, &Options::default())
+}
+
+/// Parse the Kconfig file at `path`, as configured by `options`
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser::{self, Options};
+///
+/// let dir = std::env::temp_dir()
+///     .join(format!("konf-parse-file-with-options-{}", std::process::id()));
+/// std::fs::create_dir_all(dir.join("arch")).unwrap();
+/// std::fs::write(dir.join("arch/Kconfig"), "config SMP\n bool\n").unwrap();
+/// std::fs::write(dir.join("Kconfig"), r#"source "$(KONF_EXAMPLE_SUBDIR)/Kconfig""#).unwrap();
+///
+/// std::env::set_var("KONF_EXAMPLE_SUBDIR", "arch");
+/// let kconfig = parser::parse_file(dir.join("Kconfig")).unwrap();
+/// assert!(kconfig.vars.contains_key("SMP"));
+///
+/// // unset variables are an error when `strict_env` is on
+/// std::env::remove_var("KONF_EXAMPLE_SUBDIR");
+/// let options = Options { strict_env: true, ..Default::default() };
+/// assert!(parser::parse_file_with_options(dir.join("Kconfig"), &options).is_err());
+///
+/// // sources which aren't next to the file sourcing them are looked for in `search_dirs`
+/// let include = std::env::temp_dir()
+///     .join(format!("konf-parse-file-search-dir-{}", std::process::id()));
+/// std::fs::create_dir_all(&include).unwrap();
+/// std::fs::write(include.join("Kconfig.common"), "config COMMON\n bool\n").unwrap();
+/// std::fs::write(dir.join("Kconfig.top"), r#"source "Kconfig.common""#).unwrap();
+///
+/// assert!(parser::parse_file(dir.join("Kconfig.top")).is_err());
+/// let options = Options { search_dirs: vec![include], ..Default::default() };
+/// let kconfig = parser::parse_file_with_options(dir.join("Kconfig.top"), &options).unwrap();
+/// assert!(kconfig.vars.contains_key("COMMON"));
+///
+/// // a chain of sources nested deeper than `max_source_depth` is an error
+/// let chain = std::env::temp_dir()
+///     .join(format!("konf-parse-file-source-depth-{}", std::process::id()));
+/// std::fs::create_dir_all(&chain).unwrap();
+/// for i in 0..10 {
+///     let text = format!("config LEVEL_{i}\n bool\nsource \"Kconfig.{}\"\n", i + 1);
+///     std::fs::write(chain.join(format!("Kconfig.{i}")), text).unwrap();
+/// }
+/// std::fs::write(chain.join("Kconfig.10"), "config LEVEL_10\n bool\n").unwrap();
+///
+/// let options = Options { max_source_depth: 10, ..Default::default() };
+/// let kconfig = parser::parse_file_with_options(chain.join("Kconfig.0"), &options).unwrap();
+/// assert!(kconfig.vars.contains_key("LEVEL_10"));
+///
+/// let options = Options { max_source_depth: 9, ..Default::default() };
+/// let err = parser::parse_file_with_options(chain.join("Kconfig.0"), &options).unwrap_err();
+/// let msg = err.to_string();
+/// assert!(msg.starts_with("`source` nested more than 9 deep: "));
+/// assert!(msg.contains("Kconfig.0 -> "));
+/// assert!(msg.contains("Kconfig.9 -> "));
+/// assert!(msg.ends_with("Kconfig.10"));
+/// ```
+pub fn parse_file_with_options<P: AsRef<Path>>(path: P, options: &Options) -> Result<KConfig> {
+    let (text, path) = read_file(path.as_ref())?;
+    let (kconfig, _) = parse(&text, Some(&path), path.parent(), options, false)?;
+    Ok(kconfig)
+}
+
+/// Parse the Kconfig file at `path`, also returning the warnings found along the way, such as
+/// duplicate definitions, empty menus, configs without a type and skipped text
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser;
+///
+/// let path = std::env::temp_dir()
+///     .join(format!("konf-parse-file-with-warnings-{}", std::process::id()));
+/// std::fs::write(&path, "config FOO\n bool\nmenu \"Empty\"\nendmenu\n").unwrap();
+///
+/// let (kconfig, warnings) = parser::parse_file_with_warnings(&path).unwrap();
+/// assert!(kconfig.vars.contains_key("FOO"));
+/// assert_eq!(warnings.len(), 1);
+/// assert!(warnings[0].location.ends_with(":4"));
+/// assert_eq!(warnings[0].msg, "menu \"Empty\" is empty");
+///
+/// // a duplicate symbol still parses, extending the first definition
+/// std::fs::write(&path, "config FOO\n bool\nconfig FOO\n default y\n").unwrap();
+/// let (kconfig, warnings) = parser::parse_file_with_warnings(&path).unwrap();
+/// assert_eq!(kconfig.vars["FOO"].defaults.len(), 1);
+/// assert_eq!(warnings.len(), 1);
+/// assert!(warnings[0].location.ends_with(":3"));
+/// assert!(warnings[0].msg.starts_with("duplicate definition of `FOO`, first defined at "));
+/// assert!(warnings[0].msg.ends_with(":1"));
+///
+/// std::fs::write(&path, "config FOO\n  default y\n").unwrap();
+/// let (_, warnings) = parser::parse_file_with_warnings(&path).unwrap();
+/// assert_eq!(warnings.len(), 1);
+/// assert!(warnings[0].location.ends_with(":1"));
+/// assert_eq!(warnings[0].msg, "`FOO` has a default but no type");
+/// ```
+pub fn parse_file_with_warnings<P: AsRef<Path>>(path: P) -> Result<(KConfig, Vec<Warning>)> {
+    let (text, path) = read_file(path.as_ref())?;
+    let (kconfig, state) = parse(
+        &text,
+        Some(&path),
+        path.parent(),
+        &Options::default(),
+        false,
+    )?;
+    Ok((kconfig, state.warnings))
+}
+
+/// Warn about configs without a type which have a default or are referred to by others, as
+/// their values can't be checked
+fn warn_untyped(kconfig: &KConfig, state: &mut State) {
+    let referenced: HashSet<&str> = kconfig.vars.values().flat_map(|v| v.references()).collect();
+    for var in kconfig.vars.values().filter(|v| v.ty.is_none()) {
+        let why = if !var.defaults.is_empty() {
+            "has a default"
+        } else if referenced.contains(var.name.as_str()) {
+            "is referenced"
+        } else {
+            continue;
+        };
+        state.warnings.push(Warning {
+            location: state.defined.get(&var.name).cloned().unwrap_or_default(),
+            msg: format!("`{}` {why} but no type", var.name),
+        });
+    }
+}
+
+/// Read the Kconfig file at `path`, returning its text and canonical path
+fn read_file(path: &Path) -> Result<(String, PathBuf)> {
+    let fail = |e: io::Error| {
+        let msg = format!("failed to read {}: {e}", path.display());
+        Error::new(ErrorKind::Io, msg)
+    };
+    let text = std::fs::read_to_string(path).map_err(fail)?;
+    let path = path.canonicalize().map_err(fail)?;
+    Ok((text, path))
+}
+
+/// Parse Kconfig text directly. As there is no file to resolve paths against, a relative
+/// `source` in `text` is an error; use [`parse_str_with_base`] to allow them.
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, Value};
+///
+/// let kconfig = parser::parse_str(
+///     r#"
+///     mainmenu "Example"
+///     config GCC
+///         bool "Use GCC"
+///         default y
+///     config JOBS
+///         int "Parallel jobs"
+///         default 4
+///     "#,
+/// )
+/// .unwrap();
+/// assert_eq!(kconfig.name, "Example");
+/// assert_eq!(kconfig.vars["GCC"].defaults, vec![(Value::Bool(true).into(), None)]);
+/// assert_eq!(kconfig.vars["JOBS"].defaults, vec![(Value::Int(4).into(), None)]);
+///
+/// assert!(parser::parse_str(r#"source "other/Kconfig""#).is_err());
+///
+/// // absolute paths are used as they are
+/// let path = std::env::temp_dir().join(format!("konf-parse-str-absolute-{}", std::process::id()));
+/// std::fs::write(&path, "config GENERATED\n bool\n").unwrap();
+/// let kconfig = parser::parse_str(&format!("source \"{}\"", path.display())).unwrap();
+/// assert!(kconfig.vars.contains_key("GENERATED"));
+///
+/// let err = parser::parse_str("mainmenu \"A\"\nmainmenu \"B\"\n").unwrap_err();
+/// assert_eq!(err.to_string(), r#"second `mainmenu` "B", already titled "A""#);
+///
+/// // `$(VAR)` in the title is expanded from the environment
+/// std::env::set_var("ARCH", "riscv");
+/// let kconfig = parser::parse_str(r#"mainmenu "Linux/$(ARCH) Kernel Configuration""#).unwrap();
+/// assert_eq!(kconfig.name, "Linux/riscv Kernel Configuration");
+/// ```
+///
+/// `mainmenu` is optional. Without it the configuration is named `config`, and configs at the
+/// top of the file belong to `root`:
+///
+/// ```
+/// use konf::{parser, Entry};
+///
+/// let kconfig = parser::parse_str("config FOO\n bool\n").unwrap();
+/// assert_eq!(kconfig.name, "config");
+/// assert_eq!(kconfig.root.entries, [Entry::Variable("FOO".to_string())]);
+/// ```
+///
+/// Each `menu` must be closed by its own `endmenu` and each `choice` by its own `endchoice`:
+///
+/// ```
+/// use konf::parser;
+///
+/// let err = parser::parse_str("menu \"A\"\nconfig FOO\n bool\nendchoice\n").unwrap_err();
+/// assert_eq!(err.to_string(), "expected `endmenu`, found `endchoice`");
+///
+/// let err = parser::parse_str("choice\nconfig GCC\n bool\nendmenu\n").unwrap_err();
+/// assert_eq!(err.to_string(), "expected `endchoice`, found `endmenu`");
+///
+/// let err = parser::parse_str("choice\nconfig GCC\n bool\nconfig CLANG\n bool\n").unwrap_err();
+/// assert_eq!(err.to_string(), "`choice` is missing its `endchoice`");
+///
+/// let err = parser::parse_str("menu \"A\"\nconfig FOO\n bool\n").unwrap_err();
+/// assert_eq!(err.to_string(), "menu \"A\" is missing its `endmenu`");
+///
+/// let err = parser::parse_str("config FOO\n bool\nendmenu\n").unwrap_err();
+/// assert_eq!(err.to_string(), "`endmenu` without a matching `menu`");
+/// ```
+pub fn parse_str(text: &str) -> Result<KConfig> {
+    let (kconfig, _) = parse(text, None, None, &Options::default(), false)?;
+    Ok(kconfig)
+}
+
+/// Parse Kconfig text directly, resolving any `source` relative to `base_dir`
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser;
+///
+/// let dir = std::env::temp_dir().join(format!("konf-parse-str-with-base-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("Kconfig.debug"), "config DEBUG\n bool\n").unwrap();
+///
+/// let kconfig = parser::parse_str_with_base(r#"source "Kconfig.debug""#, &dir).unwrap();
+/// assert!(kconfig.vars.contains_key("DEBUG"));
+/// ```
+pub fn parse_str_with_base<P: AsRef<Path>>(text: &str, base_dir: P) -> Result<KConfig> {
+    let (kconfig, _) = parse(
+        text,
+        None,
+        Some(base_dir.as_ref()),
+        &Options::default(),
+        false,
+    )?;
+    Ok(kconfig)
+}
+
+/// Parse Kconfig text, recovering from errors rather than stopping at the first one. An entry
+/// which fails to parse is skipped up to the next top level keyword (`config`, `menu`,
+/// `source`...), and every error is returned alongside what could be parsed. Any `source` is
+/// resolved relative to `base_dir`.
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser;
+///
+/// let (kconfig, errors) = parser::parse_str_recovering(
+///     r#"
+///     config GOOD_A
+///         bool
+///     config 123
+///         bool
+///     config GOOD_B
+///         bool
+///     "#,
+///     None,
+/// );
+/// assert!(kconfig.vars.contains_key("GOOD_A"));
+/// assert!(kconfig.vars.contains_key("GOOD_B"));
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].to_string(), "line 4: Invalid name for `config`");
+/// ```
+pub fn parse_str_recovering(text: &str, base_dir: Option<&Path>) -> (KConfig, Vec<Error>) {
+    match parse(text, None, base_dir, &Options::default(), true) {
+        Ok((kconfig, state)) => (kconfig, state.errors),
+        Err(e) => (KConfig::new(), vec![e]),
+    }
+}
+
+/// Parse Kconfig text from `reader` a top level entry at a time, rather than reading it all
+/// in first, to keep down the memory used on very large files. Each `config`, `choice`,
+/// `source` and top level `menu` (with everything inside it) is read and parsed before the
+/// next one is read. `path` is the file the text is read from, if there is one, which warnings
+/// are located in and any `source` is resolved relative to. The result, warnings included, is
+/// the same as [`parse_file_with_warnings`] gives for the whole file. Sourced files are read
+/// whole.
+///
+/// # Examples
+///
+/// ```
+/// use konf::parser;
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let mut text = String::from("mainmenu \"Generated\"\n");
+/// for i in 0..100 {
+///     text += &format!("menu \"Menu {i}\"\n");
+///     text += &format!("config A_{i}\n  bool \"A {i}\" # a comment\n  default y\n");
+///     text += "  help\n    First paragraph.\n\n    config IS_NOT_A_CONFIG\n\n";
+///     text += &format!("choice\n  prompt \"Pick\"\nconfig B_{i}\n  bool\nendchoice\nendmenu\n");
+///     text += &format!("config C_{i}\n  int\n  range 0 \\\n    {i}\n  depends on A_{i} &&  \\\nB_{i}\n");
+///     text += &format!("config C_{i}\n  default 1\nmenu \"Empty {i}\"\nendmenu\n");
+/// }
+/// let path = std::env::temp_dir().join(format!("konf-parse-reader-{}", std::process::id()));
+/// std::fs::write(&path, &text).unwrap();
+/// let path = path.canonicalize().unwrap();
+///
+/// let reader = BufReader::new(File::open(&path).unwrap());
+/// let (streamed, warnings) = parser::parse_reader(reader, Some(&path)).unwrap();
+/// assert_eq!(streamed.vars.len(), 300);
+/// assert_eq!(warnings.len(), 200);
+/// assert_eq!((streamed, warnings), parser::parse_file_with_warnings(&path).unwrap());
+///
+/// // a stray `endmenu` is an error in both
+/// let text = "config A\n bool\nendmenu\nconfig B\n bool\n";
+/// assert!(parser::parse_reader(text.as_bytes(), None).is_err());
+/// assert!(parser::parse_str(text).is_err());
+/// ```
+pub fn parse_reader<R: BufRead>(reader: R, path: Option<&Path>) -> Result<(KConfig, Vec<Warning>)> {
+    let base = path.and_then(Path::parent);
+    let mut entries = Entries::new(reader);
+    let mut kconfig = KConfig::new();
+    let mut state = State::default();
+    let mut origin = (0, 0);
+    loop {
+        let text = entries
+            .next_entry()
+            .map_err(|e| Error::new(ErrorKind::Io, format!("failed to read Kconfig: {e}")))?;
+        if text.is_empty() {
+            break;
         }
-        Ok(())
+        let mut toks = Parser::new(&text);
+        toks.path = path.map(Path::to_path_buf);
+        toks.origin = origin;
+        toks.state = state;
+        kconfig
+            .root
+            .parse(base, &mut toks, &mut kconfig.vars, None)?;
+        state = toks.state;
+        origin.0 += text.len();
+        origin.1 += text.matches('\n').count();
     }
+    let (kconfig, state) = finish(kconfig, state);
+    Ok((kconfig, state.warnings))
+}
+
+/// Splits Kconfig text into top level entries as it is read, for [`parse_reader`]. An entry
+/// starts at a line beginning with a top level keyword, unless it is inside a `menu` or
+/// `choice`, help text, or carried on from the line before.
+struct Entries<R> {
+    reader: R,
+    /// The line which starts the next entry, once it has been read
+    next: String,
+    /// How many `menu`s and `choice`s are open
+    depth: usize,
+    /// Set in help text, to the indent of its first line once it is known
+    help: Option<Option<usize>>,
+    /// Whether the last line ended in `\`
+    continued: bool,
 }
 
-pub fn parse_file<P: AsRef<Path>>(path: P) -> std::result::Result<KConfig, &'static str> {
-    let file_text = std::fs::read_to_string(path.as_ref());
-    if let Err(e) = file_text {
-        panic!("Failed to read: {}", e);
+impl<R: BufRead> Entries<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            next: String::new(),
+            depth: 0,
+            help: None,
+            continued: false,
+        }
+    }
+
+    /// Read the text of the next entry, which is empty at the end of the input
+    fn next_entry(&mut self) -> io::Result<String> {
+        let mut entry = std::mem::take(&mut self.next);
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(entry);
+            }
+            if self.starts_entry(&line) && !entry.trim().is_empty() {
+                self.next = line;
+                return Ok(entry);
+            }
+            entry.push_str(&line);
+        }
+    }
+
+    /// Follow along with `line`, returning whether it starts a top level entry
+    fn starts_entry(&mut self, line: &str) -> bool {
+        let text = line.trim_end();
+        let continued = std::mem::replace(&mut self.continued, text.ends_with('\\'));
+
+        // the same rule as `Parser::take_help` for where help text ends
+        if let Some(indent) = &mut self.help {
+            let depth = text.len() - text.trim_start().len();
+            if text.is_empty() || !(depth == 0 || depth < *indent.get_or_insert(depth)) {
+                return false;
+            }
+            self.help = None;
+        }
+        if continued {
+            return false;
+        }
+
+        match text.split_whitespace().next() {
+            Some("help" | "---help---") => self.help = Some(None),
+            Some("menu" | "choice") => {
+                self.depth += 1;
+                return self.depth == 1;
+            }
+            Some("endmenu" | "endchoice") => self.depth = self.depth.saturating_sub(1),
+            Some("config" | "source" | "mainmenu") => return self.depth == 0,
+            _ => {}
+        }
+        false
     }
-    let mut toks = Parser::new(file_text.as_ref().unwrap());
+}
+
+/// Parse `text`, which was read from `path` if it came from a file. Any `source` is resolved
+/// relative to `base`
+fn parse(
+    text: &str,
+    path: Option<&Path>,
+    base: Option<&Path>,
+    options: &Options,
+    recover: bool,
+) -> Result<(KConfig, State)> {
+    let mut toks = Parser::with_options(text, options.clone());
+    toks.path = path.map(Path::to_path_buf);
+    toks.recover = recover;
 
     let mut kconfig = KConfig::new();
 
     kconfig
         .root
-        .parse(path.as_ref(), &mut toks, &mut kconfig.vars)?;
+        .parse(base, &mut toks, &mut kconfig.vars, None)?;
+    Ok(finish(kconfig, toks.state))
+}
 
-    kconfig.name = kconfig.root.name.clone();
+/// Finish off a parsed `kconfig`, once all of its text has been through the parser
+fn finish(mut kconfig: KConfig, mut state: State) -> (KConfig, State) {
+    warn_untyped(&kconfig, &mut state);
 
-    Ok(kconfig)
+    // without a `mainmenu`, the names from `KConfig::new` are kept
+    if let Some(name) = &state.mainmenu {
+        kconfig.name = name.clone();
+        kconfig.root.name = name.clone();
+    }
+
+    (kconfig, state)
 }
 
 /// Return a variable/value mapping, parsed from a line of `.config`. There are a few
 /// cases that this function can handle:
 ///
+/// - `CONFIG_X=VALUE`, where ints may be written in decimal, binary (`0b`) or octal (`0o`)
+/// - `# CONFIG_X is not set`, which is the same as `CONFIG_X=n`
+///
+/// Other comments and blank lines give `None`, as does anything else which isn't a setting.
+///
+/// # Examples
+///
+/// ```
+/// use konf::{parser, Value};
+///
+/// for line in ["CONFIG_JOBS=10", "CONFIG_JOBS=0b1010", "CONFIG_JOBS=0o12"] {
+///     let (name, value) = parser::parse_config_line(line).unwrap();
+///     assert_eq!(name, "JOBS");
+///     assert_eq!(value, Value::Int(10));
+///     assert_eq!(value.to_string(), "10");
+/// }
+/// assert_eq!(parser::parse_config_line("CONFIG_MIN=-0b1").unwrap().1, Value::Int(-1));
+/// assert_eq!(
+///     parser::parse_config_line("# CONFIG_DEBUG is not set"),
+///     Some(("DEBUG".to_string(), Value::Bool(false)))
+/// );
+/// assert_eq!(parser::parse_config_line("# Linux/x86 6.1.0 Kernel Configuration"), None);
+/// assert_eq!(parser::parse_config_line("#CONFIG_JOBS=4"), None);
+/// assert_eq!(parser::parse_config_line(""), None);
+/// assert_eq!(parser::parse_config_line("   "), None);
 ///
+/// // values which don't fit in 64 bits aren't parsed
+/// let too_big = format!("CONFIG_JOBS=0b1{}", "0".repeat(63));
+/// assert_eq!(parser::parse_config_line(&too_big), None);
+/// let kconfig = parser::parse_str(&format!("config JOBS\n int\n default 0o{}", "7".repeat(22)));
+/// assert!(kconfig.is_err());
+///
+/// // the largest values which do fit
+/// let max = parser::parse_config_line("CONFIG_JOBS=9223372036854775807").unwrap();
+/// assert_eq!(max.1, Value::Int(i64::MAX));
+/// let min = parser::parse_config_line("CONFIG_JOBS=-9223372036854775808").unwrap();
+/// assert_eq!(min.1, Value::Int(i64::MIN));
+/// let max = parser::parse_config_line("CONFIG_BASE=0xffffffffffffffff").unwrap();
+/// assert_eq!(max.1, Value::from(u64::MAX));
+/// assert_eq!(parser::parse_config_line("CONFIG_BASE=0x10000000000000000"), None);
+///
+/// // strings escape `"` and `\` with a `\`, and nothing may follow the value
+/// assert_eq!(
+///     parser::parse_config_line(r#"CONFIG_CC="say \"hi\" \\o/""#),
+///     Some(("CC".to_string(), Value::String(r#"say "hi" \o/"#.to_string())))
+/// );
+/// assert_eq!(parser::parse_config_line(r#"CONFIG_CC="a"b"#), None);
+/// assert_eq!(parser::parse_config_line("CONFIG_JOBS=4 5"), None);
+///
+/// let err = parser::parse_str("config BASE\n hex\n default 0x10000000000000000\n");
+/// assert_eq!(err.unwrap_err().to_string(), "hex value `0x10000000000000000` is out of range");
+/// let err = parser::parse_str("config JOBS\n int\n range 0 99999999999999999999\n");
+/// assert_eq!(err.unwrap_err().to_string(), "int `99999999999999999999` is out of range");
+/// ```
 pub fn parse_config_line(line: &str) -> Option<(String, Value)> {
+    read_config_line(line).ok().flatten()
+}
+
+/// Parse a line of `.config` as [`parse_config_line`] does, but with an error for values which
+/// are out of range or not values at all, rather than just `None`
+pub(crate) fn read_config_line(line: &str) -> Result<Option<(String, Value)>> {
     // First, handle "is not set". If this regex matches, it really just means CONFIG_X=n.
-    let unset_match = Regex::new(r"# CONFIG_([^ ]+) is not set").unwrap();
+    static UNSET: OnceLock<Regex> = OnceLock::new();
+    let unset_match = UNSET.get_or_init(|| Regex::new(r"# CONFIG_([^ ]+) is not set").unwrap());
     if let Some(caps) = unset_match.captures(line) {
-        return Some((caps[1].to_string(), Value::Bool(false)));
+        return Ok(Some((caps[1].to_string(), Value::Bool(false))));
+    }
+    // Any other comment, like the header `make` writes, and blank lines hold no setting
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok(None);
     }
 
     // Create a parser for the line
     let mut toks = Parser::new(line);
     // Try to parse a Name
-    if let Token::Name(s) = toks.next()? {
+    if let Some(Token::Name(s)) = toks.next() {
         // then an Equals
-        if let Token::Equals = toks.next()? {
+        if let Some(Token::Equals) = toks.next() {
+            let name = s.strip_prefix("CONFIG_").unwrap_or(s);
             // Then a value
-            let v = toks.parse_value()?;
-            // And return it with the `CONFIG_` stripped from the front
-            return Some((s.strip_prefix("CONFIG_").unwrap().to_string(), v));
+            if let Some(v) = toks.parse_value()? {
+                // with nothing after it
+                if toks.peek().is_some() {
+                    let rest = toks.text[toks.span.end..].trim();
+                    let msg = format!("unexpected `{rest}` after the value of {name}");
+                    return Err(Error::new(ErrorKind::Parse, msg));
+                }
+                // And return it with the `CONFIG_` stripped from the front
+                return Ok(Some((name.to_string(), v)));
+            }
+            // Anything else after the `=`, like the `m` of a tristate, can't be stored
+            let rest = toks.text[toks.span.end..].trim();
+            if !rest.is_empty() {
+                let msg = format!("`{rest}` is not a valid value for {name}");
+                return Err(Error::new(ErrorKind::Parse, msg));
+            }
         }
     }
-    None
+    Ok(None)
 }
@@ -0,0 +1,230 @@
+//! `serde` support for [`Value`] and [`Type`], enabled by the `serde` feature.
+//!
+//! Values are stored in their most natural form: bools as booleans, ints as numbers and strings
+//! as strings. Hex values are written as `"0x..."` strings so that they survive formats which
+//! store every number as a double. A string which would be read back as hex is written as a
+//! `{"string": "0x..."}` map instead, so that it stays a string.
+//!
+//! A whole [`KConfig`] can also be exported as JSON with [`KConfig::to_json`], or with the
+//! `yaml` feature as YAML with `KConfig::to_yaml`.
+
+use super::*;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::json;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Int(i) => serializer.serialize_i64(*i),
+            Self::Hex(_) => serializer.collect_str(self),
+            Self::String(s) if Hex::parse(s).is_some() => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("string", s)?;
+                map.end()
+            }
+            Self::String(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a bool, an integer, a string or a {\"string\": ...} map")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("int {v} is out of range")))
+    }
+
+    // A string which looks like `0x...` is taken to be a hex value, since that is how they
+    // are serialized
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Value, E> {
+        match Hex::parse(v) {
+            Some(h) => Ok(Value::Hex(h)),
+            None => Ok(Value::String(v.to_string())),
+        }
+    }
+
+    // A string which would otherwise be read as hex
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Value, A::Error> {
+        let value = match map.next_entry::<String, String>()? {
+            Some((key, s)) if key == "string" => Value::String(s),
+            _ => return Err(de::Error::custom("expected a map of `string` to a string")),
+        };
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom("expected only a `string` in the map"));
+        }
+        Ok(value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl Serialize for Type {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Type {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Type::new(&s).ok_or_else(|| de::Error::custom(format!("unknown type `{s}`")))
+    }
+}
+
+impl Variable {
+    fn to_json(&self, kconfig: &KConfig) -> serde_json::Value {
+        json!({
+            "config": self.name,
+            "type": self.ty,
+            "desc": self.desc,
+            "default": kconfig.default_value(&self.name),
+            "value": self.value,
+            "range": kconfig.range_of(self).ok().flatten(),
+            "depends": self.depends.as_ref().map(Expr::to_string),
+            "help": self.help,
+        })
+    }
+}
+
+impl Menu {
+    fn entries_json(&self, kconfig: &KConfig) -> Vec<serde_json::Value> {
+        self.entries
+            .iter()
+            .filter_map(|ent| match ent {
+                Entry::Menu(m) => Some(m.to_json(kconfig)),
+                Entry::Variable(s) => kconfig.vars.get(s).map(|v| v.to_json(kconfig)),
+                Entry::Choice(c) => Some(json!({
+                    "choice": c.prompt,
+                    "optional": c.optional,
+                    "default": c.default,
+                    "entries": c.members.iter()
+                        .filter_map(|m| kconfig.vars.get(m))
+                        .map(|v| v.to_json(kconfig))
+                        .collect::<Vec<_>>(),
+                })),
+                Entry::SourceComment(_) => None,
+            })
+            .collect()
+    }
+
+    fn to_json(&self, kconfig: &KConfig) -> serde_json::Value {
+        json!({
+            "menu": self.name,
+            "entries": self.entries_json(kconfig),
+        })
+    }
+}
+
+impl KConfig {
+    /// Export the menu tree as JSON, for tools like web based config editors. Menus become
+    /// `{"menu": name, "entries": [...]}` objects and each config lists its `type`, `desc`,
+    /// `default`, current `value`, `range`, `depends` expression and `help` text, with `null` for
+    /// anything unset. Symbols at the ends of a `range` are given as their current values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{Entry, KConfig, Menu, Type, Value, Variable};
+    ///
+    /// let mut kconfig = KConfig::new();
+    /// kconfig.name = "Example".to_string();
+    /// let mut build = Menu::new("Build");
+    /// for name in ["GCC", "CLANG"] {
+    ///     let mut var = Variable::new(name);
+    ///     var.ty = Some(Type::Bool);
+    ///     var.defaults.push((Value::Bool(name == "GCC").into(), None));
+    ///     var.help = Some(format!("Build with {name}."));
+    ///     kconfig.add_var(var);
+    ///     build.entries.push(Entry::Variable(name.to_string()));
+    /// }
+    /// kconfig.root.entries.push(Entry::Menu(build));
+    ///
+    /// let json: serde_json::Value = serde_json::from_str(&kconfig.to_json()).unwrap();
+    /// assert_eq!(json["mainmenu"], "Example");
+    /// let build = &json["entries"][0];
+    /// assert_eq!(build["menu"], "Build");
+    /// assert_eq!(build["entries"][0]["config"], "GCC");
+    /// assert_eq!(build["entries"][0]["type"], "bool");
+    /// assert_eq!(build["entries"][0]["default"], true);
+    /// assert_eq!(build["entries"][0]["help"], "Build with GCC.");
+    /// assert_eq!(build["entries"][1]["config"], "CLANG");
+    /// assert_eq!(build["entries"][1]["value"], serde_json::Value::Null);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let root = json!({
+            "mainmenu": self.name,
+            "entries": self.root.entries_json(self),
+        });
+        serde_json::to_string_pretty(&root).unwrap()
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl KConfig {
+    /// Export the effective value of every symbol as a flat YAML mapping from name to value,
+    /// in declaration order. Unset symbols are `null`, and hex values are `"0x..."` strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use konf::{parser, Value};
+    /// use std::collections::HashMap;
+    ///
+    /// let kconfig = parser::parse_str(
+    ///     r#"
+    ///     config GCC
+    ///         bool
+    ///         default y
+    ///     config JOBS
+    ///         int
+    ///         default 4
+    ///     config BASE
+    ///         hex
+    ///         default 0x1000
+    ///     config CC
+    ///         string
+    ///         default "gcc"
+    ///     config LTO
+    ///         bool
+    ///     "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let yaml = kconfig.to_yaml();
+    /// assert!(yaml.starts_with("GCC: true\nJOBS: 4\nBASE: '0x1000'\n"));
+    /// let back: HashMap<String, Option<Value>> = serde_yaml::from_str(&yaml).unwrap();
+    /// assert_eq!(back.len(), 5);
+    /// for (name, value) in kconfig.save_effective() {
+    ///     assert_eq!(back[&name], value);
+    /// }
+    /// ```
+    pub fn to_yaml(&self) -> String {
+        let mut map = serde_yaml::Mapping::new();
+        for (k, v) in self.save_effective() {
+            map.insert(k.into(), serde_yaml::to_value(v).unwrap());
+        }
+        serde_yaml::to_string(&map).unwrap()
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const KCONFIG: &str = r#"
+mainmenu "Test"
+config GCC
+    bool "Use GCC"
+    default y
+config DEBUG
+    bool "Enable debugging"
+config JOBS
+    int "Parallel jobs"
+    default 4
+"#;
+
+/// A fresh directory holding the test Kconfig, for the binary to run in
+fn workdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("konf-cli-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Kconfig"), KCONFIG).unwrap();
+    dir
+}
+
+fn konf(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_konf"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn print_mode_prints_without_saving() {
+    let dir = workdir("print");
+    let out = konf(&dir, &["--mode", "print"]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("mainmenu \"Test\""));
+    assert!(!dir.join(".config").exists());
+}
+
+#[test]
+fn defconfig_mode_writes_to_output() {
+    let dir = workdir("defconfig");
+    std::fs::write(dir.join(".config"), "CONFIG_DEBUG=y\nCONFIG_JOBS=4\n").unwrap();
+    let out = konf(&dir, &["--mode", "defconfig", "--output", "small"]);
+    assert!(out.status.success());
+    let saved = std::fs::read_to_string(dir.join("small")).unwrap();
+    assert_eq!(saved, "CONFIG_DEBUG=y\n");
+}
+
+#[test]
+fn olddefconfig_mode_fills_in_new_symbols() {
+    let dir = workdir("olddefconfig");
+    std::fs::write(dir.join(".config"), "CONFIG_DEBUG=y\n").unwrap();
+    let out = konf(&dir, &["-m", "olddefconfig"]);
+    assert!(out.status.success());
+    let saved = std::fs::read_to_string(dir.join(".config")).unwrap();
+    assert!(saved.contains("CONFIG_GCC=y"));
+    assert!(saved.contains("CONFIG_DEBUG=y"));
+    assert!(saved.contains("CONFIG_JOBS=4"));
+}
+
+#[test]
+fn unknown_mode_is_rejected() {
+    let dir = workdir("unknown-mode");
+    let out = konf(&dir, &["--mode", "menuconfig"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("menuconfig"));
+}
+
+#[test]
+fn missing_config_file_falls_back_to_defaults() {
+    let dir = workdir("missing-config");
+    let out = konf(&dir, &["--config-file", "fresh.config"]);
+    assert!(out.status.success());
+    let saved = std::fs::read_to_string(dir.join("fresh.config")).unwrap();
+    assert!(saved.contains("CONFIG_GCC=y"));
+    assert!(saved.contains("CONFIG_JOBS=4"));
+}
+
+#[test]
+fn config_file_is_read_from_the_given_path() {
+    let dir = workdir("config-file");
+    std::fs::write(dir.join("my.config"), "CONFIG_JOBS=16\n").unwrap();
+    let out = konf(&dir, &["-c", "my.config", "-m", "defconfig"]);
+    assert!(out.status.success());
+    let saved = std::fs::read_to_string(dir.join("defconfig")).unwrap();
+    assert_eq!(saved, "CONFIG_JOBS=16\n");
+}
+
+#[test]
+fn header_is_written_when_asked_for() {
+    let dir = workdir("header");
+    std::fs::write(dir.join(".config"), "CONFIG_DEBUG=y\n").unwrap();
+    let out = konf(&dir, &["--header", "out.h"]);
+    assert!(out.status.success());
+    let header = std::fs::read_to_string(dir.join("out.h")).unwrap();
+    assert!(header.contains("#define CONFIG_GCC 1\n"));
+    assert!(header.contains("#define CONFIG_DEBUG 1\n"));
+    assert!(header.contains("#define CONFIG_JOBS 4\n"));
+
+    let out = konf(&dir, &["--header", "missing-dir/out.h"]);
+    assert!(!out.status.success());
+    let out = konf(&dir, &["--mode", "print", "--header", "missing-dir/out.h"]);
+    assert!(!out.status.success());
+}
+
+#[test]
+fn failures_exit_with_an_error_status() {
+    let dir = workdir("failures");
+    let out = konf(&dir, &["does-not-exist/Kconfig"]);
+    assert!(!out.status.success());
+
+    std::fs::write(dir.join(".config"), "CONFIG_JOBS=lots\n").unwrap();
+    let out = konf(&dir, &[]);
+    assert!(!out.status.success());
+
+    std::fs::write(dir.join(".config"), "CONFIG_JOBS=4\n").unwrap();
+    let out = konf(&dir, &["--output", "missing-dir/.config"]);
+    assert!(!out.status.success());
+}